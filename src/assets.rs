@@ -1,12 +1,12 @@
 use crate::ustr::*;
 use async_trait::async_trait;
-use futures::TryFutureExt;
-use futures::{future::try_join_all, try_join};
+use futures::{future::join_all, join};
+use futures::{Future, TryFutureExt};
 use macroquad::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::convert::TryInto;
-use std::iter::FromIterator;
-use std::path::{Path, PathBuf};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
 
 mod animated_sprite;
 
@@ -14,6 +14,10 @@ pub use animated_sprite::AnimatedSprite;
 
 use crate::SpriteComponent;
 
+const MANIFEST_PATH: &str = "assets/manifest.json";
+/// Texture drawn in place of any name the manifest doesn't have.
+const MISSING_TEXTURE: &str = "missing";
+
 #[async_trait]
 pub trait Asset {
     async fn load<'a>(path: &'a Path) -> anyhow::Result<Self>
@@ -65,12 +69,27 @@ impl Asset for Texture2D {
     }
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
-pub struct AnimatedSpriteId(usize);
+/// Loads a texture straight away rather than through macroquad's async loader, for textures
+/// that are first needed mid-frame.
+fn load_texture_now(path: &Path) -> anyhow::Result<Texture2D> {
+    let bytes = std::fs::read(path)?;
+    let texture = Texture2D::from_file_with_format(&bytes, None);
+    texture.set_filter(FilterMode::Nearest);
+    Ok(texture)
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AnimatedSpriteId(Ustr);
 
 impl Default for AnimatedSpriteId {
     fn default() -> Self {
-        Self(0)
+        Self(ustr("maribelle"))
+    }
+}
+
+impl AnimatedSpriteId {
+    pub fn name(&self) -> Ustr {
+        self.0
     }
 }
 
@@ -78,11 +97,11 @@ impl AssetId for AnimatedSpriteId {
     type Asset = AnimatedSprite;
 
     fn get<'a>(&self, assets: &'a Assets) -> &'a Self::Asset {
-        &assets.animated_sprites[self.0].get()
+        assets.animated_sprites[&self.0].get()
     }
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TextureId {
     TextureId(Ustr),
     AnimatedSpriteId(AnimatedSpriteId),
@@ -90,7 +109,7 @@ pub enum TextureId {
 
 impl Default for TextureId {
     fn default() -> Self {
-        Self::TextureId(ustr("missing"))
+        Self::TextureId(ustr(MISSING_TEXTURE))
     }
 }
 
@@ -117,7 +136,7 @@ impl AssetId for TextureId {
 
     fn get<'a>(&self, assets: &'a Assets) -> &'a Self::Asset {
         match self {
-            TextureId::TextureId(name) => &assets.textures.0[&assets.asset_data.textures[name]],
+            TextureId::TextureId(name) => assets.named_texture(*name),
 
             TextureId::AnimatedSpriteId(id) => &assets.get(id).src,
         }
@@ -138,100 +157,437 @@ impl<T: Asset> Default for AssetMap<T> {
 }
 
 impl<T: Asset + 'static> AssetMap<T> {
-    async fn from_iter<I: IntoIterator<Item = Ustr>>(iter: I) -> anyhow::Result<Self> {
+    /// Loads every path, keyed by path. Failures are added to `errors` rather than returned.
+    async fn from_iter<I: IntoIterator<Item = Ustr>>(
+        iter: I,
+        progress: &LoadProgress,
+        errors: &mut Vec<String>,
+    ) -> Self {
         let paths: Vec<_> = iter.into_iter().collect();
-        Ok(Self(UstrMap::from_iter(
-            try_join_all(
-                paths
-                    .iter()
-                    .map(|path| T::load(Path::new(path.as_str())).map_ok(move |a| (*path, a))),
-            )
-            .await?,
-        )))
+        let loads = paths
+            .iter()
+            .map(|&path| (path, path, T::load(Path::new(path.as_str()))));
+        Self(
+            load_all(loads, progress, errors)
+                .await
+                .into_iter()
+                .collect(),
+        )
     }
+}
 
-    async fn reload(&mut self) -> anyhow::Result<()> {
-        try_join_all(self.0.iter_mut().map(|(k, v)| {
-            T::load(Path::new(k.as_str())).map_ok(move |new_asset| {
-                v.delete();
-                *v = new_asset;
-            })
-        }))
-        .await?;
-        Ok(())
+/// Turns the failures gathered by `load_all` into one error listing them all.
+fn report_failures(errors: Vec<String>) -> anyhow::Result<()> {
+    if !errors.is_empty() {
+        anyhow::bail!(
+            "Failed to load {} assets:\n{}",
+            errors.len(),
+            errors.join("\n")
+        );
     }
+    Ok(())
 }
 
+/// Awaits every load rather than stopping at the first failure, so every bad path gets reported
+/// at once. Each load comes with a key to return it under and the path to report if it fails.
+async fn load_all<K, T, F>(
+    loads: impl IntoIterator<Item = (K, Ustr, F)>,
+    progress: &LoadProgress,
+    errors: &mut Vec<String>,
+) -> Vec<(K, T)>
+where
+    F: Future<Output = anyhow::Result<T>>,
+{
+    let (keys, loads): (Vec<_>, Vec<_>) = loads
+        .into_iter()
+        .map(|(key, path, load)| ((key, path), load))
+        .unzip();
+    progress.expect(keys.len());
+    let results = join_all(loads.into_iter().map(|load| {
+        load.map_ok(|asset| {
+            progress.advance();
+            asset
+        })
+    }))
+    .await;
+    let mut loaded = Vec::new();
+    for ((key, path), result) in keys.into_iter().zip(results) {
+        match result {
+            Ok(asset) => loaded.push((key, asset)),
+            Err(e) => errors.push(format!("{}: {:#}", path, e)),
+        }
+    }
+    loaded
+}
+
+/// Everything in `assets/manifest.json`: each kind of asset by name, mapped to its path.
 #[derive(Deserialize)]
-struct AssetData {
+struct Manifest {
     textures: UstrMap<Ustr>,
+    animated_sprites: UstrMap<Ustr>,
     sprites: UstrMap<SpriteComponent>,
+    fonts: UstrMap<FontData>,
+    /// Textures to load at startup. The rest are loaded the first time they're drawn.
+    #[serde(default)]
+    preload: Vec<Ustr>,
+    /// Extra settings for textures used as tilesets, keyed by texture name.
+    #[serde(default)]
+    tilesets: UstrMap<TilesetData>,
+}
+
+#[derive(Deserialize)]
+struct TilesetData {
+    /// Keyed by the tile index placed in the map, which is also the first frame's.
+    #[serde(default)]
+    animated_tiles: HashMap<u32, AnimatedTileData>,
+    /// Tiles for the editor's autotiling brush, indexed by which neighbours are also from this
+    /// list: 1 above, 2 right, 4 below, 8 left.
+    #[serde(default)]
+    autotile: Option<[u32; 16]>,
+}
+
+#[derive(Deserialize)]
+struct AnimatedTileData {
+    frames: Vec<u32>,
+    /// How long each frame shows, in milliseconds like Aseprite's.
+    durations: Vec<f32>,
+}
+
+impl AnimatedTileData {
+    /// Expands into the tile to show on each step, the same way animated sprites are.
+    fn ticks(&self) -> Vec<u32> {
+        let frame_per_ms = 60.0 / 1000.0;
+        self.frames
+            .iter()
+            .zip(&self.durations)
+            .flat_map(|(&tile, &duration)| {
+                std::iter::repeat_n(tile, ((duration * frame_per_ms) as usize).max(1))
+            })
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct FontData {
+    path: Ustr,
+    /// Texture ids for the font's pages, in the order the .fnt numbers them.
+    pages: Vec<Ustr>,
+}
+
+pub struct Font {
+    pub bmfont: bmfont::BMFont,
+    pages: Vec<Ustr>,
+}
+
+impl Font {
+    async fn load(data: &FontData) -> anyhow::Result<Self> {
+        let bytes = load_file(data.path.as_str()).await?;
+        Ok(Self {
+            bmfont: bmfont::BMFont::new(
+                std::io::Cursor::new(bytes),
+                bmfont::OrdinateOrientation::TopToBottom,
+            )?,
+            pages: data.pages.clone(),
+        })
+    }
+
+    /// The texture holding one page of this font's glyphs.
+    pub fn page(&self, page: u32) -> TextureId {
+        self.pages
+            .get(page as usize)
+            .map(|&name| TextureId::TextureId(name))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FontId(Ustr);
+
+impl Default for FontId {
+    fn default() -> Self {
+        Self(ustr("default"))
+    }
+}
+
+impl AssetId for FontId {
+    type Asset = Font;
+
+    fn get<'a>(&self, assets: &'a Assets) -> &'a Self::Asset {
+        &assets.fonts[&self.0]
+    }
+}
+
+/// How far `Assets::new` has got, so a loading screen can be drawn while it runs.
+#[derive(Default)]
+pub struct LoadProgress {
+    loaded: Cell<usize>,
+    total: Cell<usize>,
+}
+
+impl LoadProgress {
+    fn expect(&self, count: usize) {
+        self.total.set(self.total.get() + count);
+    }
+
+    fn advance(&self) {
+        self.loaded.set(self.loaded.get() + 1);
+    }
+
+    pub fn loaded(&self) -> usize {
+        self.loaded.get()
+    }
+
+    /// The total only grows as `Assets::new` discovers what it has to load.
+    pub fn total(&self) -> usize {
+        self.total.get()
+    }
 }
 
 pub struct Assets {
     pub char_concept: TextureId,
-    // pub char_sprite: AssetWrapper<AnimatedSprite>,
-    pub char_sprite: AnimatedSpriteId,
-    pub animated_sprites: Vec<AssetWrapper<AnimatedSprite>>,
-    textures: AssetMap<Texture2D>,
-    asset_data: AssetData,
-    pub font: bmfont::BMFont,
+    animated_sprites: UstrMap<AssetWrapper<AnimatedSprite>>,
+    /// Keyed by path, filled in as each texture is first needed.
+    textures: UstrMap<OnceCell<Texture2D>>,
+    manifest: Manifest,
+    fonts: UstrMap<Font>,
+    /// CPU-side copies of textures, read back the first time one of their pixels is sampled.
+    images: RefCell<HashMap<TextureId, Image>>,
+    /// Texture names asked for that aren't in the manifest, so each is only reported once.
+    unknown_textures: RefCell<HashSet<Ustr>>,
+    /// The tile to show on each step for every animated tile, keyed by tileset name and tile.
+    animated_tiles: HashMap<(Ustr, u32), Vec<u32>>,
 }
 
 impl Assets {
-    pub async fn new() -> anyhow::Result<Self> {
+    pub async fn new(progress: &LoadProgress) -> anyhow::Result<Self> {
         // let (char_concept, char_sprite) = futures::try_join!(
         //     AssetWrapper::new("assets/charconcept.png"),
         //     AssetWrapper::new("assets/maribelle.json")
         // )?;
 
-        let animated_sprites = try_join_all([
-            AssetWrapper::new("assets/maribelle.json"),
-            AssetWrapper::new("assets/ghost.json"),
-        ])
-        .await
-        .unwrap();
-
-        let asset_data: AssetData =
-            serde_json::from_str(&load_string("assets/asset_data.json").await?)?;
-
-        let textures = AssetMap::from_iter(asset_data.textures.values().cloned()).await?;
+        let manifest: Manifest = serde_json::from_str(&load_string(MANIFEST_PATH).await?)?;
+
+        let mut errors = Vec::new();
+        let sprite_loads = manifest
+            .animated_sprites
+            .iter()
+            .map(|(&name, &path)| (name, path, AssetWrapper::new(path.as_str())));
+        let animated_sprites = load_all(sprite_loads, progress, &mut errors)
+            .await
+            .into_iter()
+            .collect();
+        let textures = Self::preload_textures(&manifest, progress, &mut errors).await;
+        let font_loads = manifest
+            .fonts
+            .iter()
+            .map(|(&name, data)| (name, data.path, Font::load(data)));
+        let fonts = load_all(font_loads, progress, &mut errors)
+            .await
+            .into_iter()
+            .collect();
+        report_failures(errors)?;
+
+        let animated_tiles = manifest
+            .tilesets
+            .iter()
+            .flat_map(|(&name, tileset)| {
+                tileset
+                    .animated_tiles
+                    .iter()
+                    .map(move |(&tile, animated)| ((name, tile), animated.ticks()))
+            })
+            .collect();
 
         Ok(Assets {
             char_concept: TextureId::TextureId(ustr("concept")),
-            char_sprite: AnimatedSpriteId(0),
             animated_sprites, // spritesheets: Default::default(),
             textures,
-            asset_data,
-            font: bmfont::BMFont::new(
-                std::io::Cursor::new(&include_bytes!("../assets/font.fnt")[..]),
-                bmfont::OrdinateOrientation::TopToBottom,
-            )?,
+            manifest,
+            fonts,
+            images: Default::default(),
+            unknown_textures: Default::default(),
+            animated_tiles,
+        })
+    }
+
+    async fn preload_textures(
+        manifest: &Manifest,
+        progress: &LoadProgress,
+        errors: &mut Vec<String>,
+    ) -> UstrMap<OnceCell<Texture2D>> {
+        let mut textures: UstrMap<_> = manifest
+            .textures
+            .values()
+            .map(|&path| (path, OnceCell::new()))
+            .collect();
+        if !manifest.textures.contains_key(&ustr(MISSING_TEXTURE)) {
+            errors.push(format!(
+                "{}: no \"{}\" texture to stand in for unknown ones",
+                MANIFEST_PATH, MISSING_TEXTURE
+            ));
+        }
+        let mut preload = Vec::new();
+        for name in &manifest.preload {
+            match manifest.textures.get(name) {
+                Some(&path) => preload.push(path),
+                None => errors.push(format!(
+                    "{}: preload names {}, which isn't in textures",
+                    MANIFEST_PATH, name
+                )),
+            }
+        }
+        for (path, texture) in AssetMap::<Texture2D>::from_iter(preload, progress, errors)
+            .await
+            .0
+        {
+            textures.insert(path, OnceCell::from(texture));
+        }
+        textures
+    }
+
+    /// The texture the manifest calls `name`, or the missing texture if it has no such name.
+    fn named_texture(&self, name: Ustr) -> &Texture2D {
+        if let Some(&path) = self.manifest.textures.get(&name) {
+            return self.texture(path);
+        }
+        if self.unknown_textures.borrow_mut().insert(name) {
+            println!("No texture named {} in {}", name, MANIFEST_PATH);
+        }
+        self.texture(self.manifest.textures[&ustr(MISSING_TEXTURE)])
+    }
+
+    fn texture(&self, path: Ustr) -> &Texture2D {
+        self.textures[&path].get_or_init(|| {
+            load_texture_now(Path::new(path.as_str())).unwrap_or_else(|e| {
+                println!("Failed to load {}: {}", path, e);
+                Texture2D::empty()
+            })
         })
     }
 
+    pub fn get_sprite<S>(&self, id: S) -> AnimatedSpriteId
+    where
+        S: Into<Ustr>,
+    {
+        AnimatedSpriteId(id.into())
+    }
+
+    /// Every loaded animated sprite, sorted by name.
+    pub fn animated_sprite_ids(&self) -> Vec<AnimatedSpriteId> {
+        let mut ids: Vec<_> = self
+            .animated_sprites
+            .keys()
+            .map(|&name| AnimatedSpriteId(name))
+            .collect();
+        ids.sort_by_key(|id| id.0.as_str());
+        ids
+    }
+
+    /// The name of every texture in the manifest, sorted.
+    pub fn texture_names(&self) -> Vec<Ustr> {
+        let mut names: Vec<_> = self.manifest.textures.keys().copied().collect();
+        names.sort_by_key(|name| name.as_str());
+        names
+    }
+
     pub fn get_texture<S>(&self, id: S) -> TextureId
     where
-        S: TryInto<Ustr>,
+        S: Into<Ustr>,
     {
-        TextureId::TextureId(
-            id.try_into()
-                .map_err(|_e| String::from("Texture ID too big"))
-                .unwrap(),
-        )
+        TextureId::TextureId(id.into())
+    }
+
+    /// What to draw in place of `tile` from `tileset`, `ticks` steps in. Tiles that aren't
+    /// animated come back unchanged.
+    pub fn animated_tile(&self, tileset: TextureId, tile: u32, ticks: u64) -> u32 {
+        let name = match tileset {
+            TextureId::TextureId(name) => name,
+            TextureId::AnimatedSpriteId(_) => return tile,
+        };
+        match self.animated_tiles.get(&(name, tile)) {
+            Some(frames) if !frames.is_empty() => frames[(ticks % frames.len() as u64) as usize],
+            _ => tile,
+        }
+    }
+
+    /// The autotiling set configured for `tileset`, if it has one.
+    pub fn autotile_set(&self, tileset: TextureId) -> Option<&[u32; 16]> {
+        match tileset {
+            TextureId::TextureId(name) => self.manifest.tilesets.get(&name)?.autotile.as_ref(),
+            TextureId::AnimatedSpriteId(_) => None,
+        }
+    }
+
+    /// Finds the texture whose path in the manifest refers to the same file as `path`.
+    pub fn find_texture_by_path(&self, path: &Path) -> Option<TextureId> {
+        let path = normalize_path(path);
+        self.manifest
+            .textures
+            .iter()
+            .find(|(_, texture_path)| normalize_path(Path::new(texture_path.as_str())) == path)
+            .map(|(name, _)| TextureId::TextureId(*name))
     }
 
     pub fn get<T: AssetId>(&self, id: &T) -> &T::Asset {
         id.get(self)
     }
 
+    /// Returns the alpha of a texture's pixel, or `None` if the point lies outside the texture.
+    pub fn sample_alpha(&self, id: &TextureId, point: Vec2) -> Option<f32> {
+        let mut images = self.images.borrow_mut();
+        let image = images
+            .entry(*id)
+            .or_insert_with(|| self.get(id).get_texture_data());
+        if point.x < 0.0
+            || point.y < 0.0
+            || point.x >= image.width() as f32
+            || point.y >= image.height() as f32
+        {
+            return None;
+        }
+        Some(image.get_pixel(point.x as u32, point.y as u32).a)
+    }
+
     pub async fn reload(&mut self) -> anyhow::Result<()> {
-        try_join!(
-            self.textures.reload(),
+        // Anything not preloaded comes back the next time it's drawn
+        for texture in self.textures.values_mut() {
+            if let Some(texture) = texture.take() {
+                texture.delete();
+            }
+        }
+        let progress = LoadProgress::default();
+        let mut errors = Vec::new();
+        let (textures, sprite_results) = join!(
+            Self::preload_textures(&self.manifest, &progress, &mut errors),
             // self.char_sprite.reload(),
             // try_join_all(self.spritesheets.values_mut().map(|v| { v.reload() }))
-            try_join_all(self.animated_sprites.iter_mut().map(|s| s.reload()))
-        )?;
-        Ok(())
+            join_all(self.animated_sprites.values_mut().map(|s| {
+                let path = s.path.clone();
+                s.reload()
+                    .map_err(move |e| format!("{}: {:#}", path.display(), e))
+            }))
+        );
+        self.textures = textures;
+        self.images.borrow_mut().clear();
+        errors.extend(sprite_results.into_iter().filter_map(Result::err));
+        report_failures(errors)
+    }
+}
+
+/// Resolves `.` and `..` without touching the filesystem, so that paths relative to a map file
+/// can be compared against the ones listed in the manifest.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
     }
+    normalized
 }
@@ -1,5 +1,6 @@
 use super::Asset;
-use crate::types::Rect;
+use crate::types::{Direction, Rect};
+use crate::ustr::*;
 use async_trait::async_trait;
 use macroquad::prelude::*;
 use std::collections::HashMap;
@@ -7,7 +8,9 @@ use std::path::Path;
 
 mod deserialize {
     use crate::types::Rect;
-    use serde::Deserialize;
+    use serde::de::{MapAccess, SeqAccess, Visitor};
+    use serde::{Deserialize, Deserializer};
+    use std::fmt;
 
     #[derive(Deserialize)]
     #[serde(remote = "Rect")]
@@ -71,8 +74,50 @@ mod deserialize {
         frame_tags: Vec<FrameTag>,
     }
 
+    /// Aseprite exports frames either as an array or as an object keyed by filename. Both are
+    /// read in the order they appear in the file, which is the frame order either way.
+    fn deserialize_frames<'de, D>(deserializer: D) -> Result<Vec<Frame>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct FramesVisitor;
+
+        impl<'de> Visitor<'de> for FramesVisitor {
+            type Value = Vec<Frame>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an array of frames or a map of filenames to frames")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut frames = Vec::new();
+                while let Some(frame) = seq.next_element()? {
+                    frames.push(frame);
+                }
+                Ok(frames)
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut frames = Vec::new();
+                while let Some((_, frame)) = map.next_entry::<String, Frame>()? {
+                    frames.push(frame);
+                }
+                Ok(frames)
+            }
+        }
+
+        deserializer.deserialize_any(FramesVisitor)
+    }
+
     #[derive(Deserialize)]
     pub struct SpriteSheet {
+        #[serde(deserialize_with = "deserialize_frames")]
         frames: Vec<Frame>,
         meta: Meta,
     }
@@ -86,6 +131,12 @@ mod deserialize {
                     .iter()
                     .map(|t| t.convert(&self.frames, 60.0))
                     .collect(),
+                tags: self
+                    .meta
+                    .frame_tags
+                    .iter()
+                    .map(|t| t.name.clone())
+                    .collect(),
             }
         }
         pub fn get_image_filename(&self) -> &str {
@@ -105,6 +156,8 @@ pub struct Frame {
 struct SpriteInfo {
     frames: Vec<Frame>,
     animations: HashMap<String, Vec<usize>>,
+    /// Animation names in the order the sheet lists them.
+    tags: Vec<String>,
 }
 
 pub struct AnimatedSprite {
@@ -132,18 +185,52 @@ impl AnimatedSprite {
         })
     }
 
+    /// Frames past the end of the animation wrap around rather than falling back to the first
+    /// frame of the sheet.
     pub fn get_anim_frame(&self, anim: &str, frame: usize) -> &Frame {
         let frame_id = self
             .info
             .animations
             .get(anim)
-            .and_then(|anim_data| anim_data.get(frame))
-            .cloned()
+            .filter(|anim_data| !anim_data.is_empty())
+            .map(|anim_data| anim_data[frame % anim_data.len()])
             .unwrap_or(0);
 
         &self.info.frames[frame_id]
     }
 
+    /// Picks the tag to play for `base` facing `dir`, and whether it needs flipping.
+    ///
+    /// Directional tags are `base` followed by `Back`, `Front`, `Left` or `Right`. Sheets are
+    /// drawn facing right, so facing left without a `Left` tag plays `Right` flipped. Without
+    /// any directional tag this falls back to `base` itself, or to `Idle` if `base` is empty.
+    pub fn anim_for(&self, base: &str, dir: Direction) -> (Ustr, bool) {
+        let has = |name: &str| self.info.animations.contains_key(name);
+        let suffix = match dir {
+            Direction::Up => "Back",
+            Direction::Down => "Front",
+            Direction::Left => "Left",
+            Direction::Right => "Right",
+        };
+        let directional = format!("{}{}", base, suffix);
+        if has(&directional) {
+            return (ustr(&directional), false);
+        }
+        let right = format!("{}Right", base);
+        if dir == Direction::Left && has(&right) {
+            return (ustr(&right), true);
+        }
+        if base.is_empty() {
+            (ustr("Idle"), false)
+        } else {
+            (ustr(base), false)
+        }
+    }
+
+    pub fn first_animation(&self) -> Option<&str> {
+        self.info.tags.first().map(String::as_str)
+    }
+
     pub fn get_anim_length(&self, anim: &str) -> usize {
         self.info
             .animations
@@ -192,3 +279,25 @@ impl From<FileError> for SpritesheetImportError {
         Self::FileError(v)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(json: &str) -> SpriteInfo {
+        serde_json::from_str::<deserialize::SpriteSheet>(json)
+            .unwrap()
+            .convert()
+    }
+
+    #[test]
+    fn array_and_hash_frames_load_the_same() {
+        let array = load(include_str!("../../tests/fixtures/aseprite_array.json"));
+        let hash = load(include_str!("../../tests/fixtures/aseprite_hash.json"));
+        // "sprite 10" sorts before "sprite 2" as a string, but comes last in the file
+        let xs = |info: &SpriteInfo| info.frames.iter().map(|f| f.src.x).collect::<Vec<_>>();
+        assert_eq!(xs(&array), [0.0, 16.0, 32.0]);
+        assert_eq!(xs(&hash), xs(&array));
+        assert_eq!(hash.animations["idle"], array.animations["idle"]);
+    }
+}
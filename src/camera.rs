@@ -0,0 +1,204 @@
+use crate::types;
+use macroquad::{camera::Camera as _, prelude::*};
+
+/// Offsets the camera by decaying random noise. The camera itself is never moved, so it always
+/// comes back to exactly where it was.
+#[derive(Clone, Copy)]
+struct Shake {
+    intensity: f32,
+    duration: f32,
+    remaining: f32,
+}
+
+impl Shake {
+    fn offset(&self) -> Vec2 {
+        let strength = self.intensity * self.remaining / self.duration;
+        vec2(
+            macroquad::rand::gen_range(-1.0, 1.0),
+            macroquad::rand::gen_range(-1.0, 1.0),
+        ) * strength
+    }
+}
+
+/// The view onto the world, shared by gameplay and the editor.
+#[derive(Clone)]
+pub struct Camera {
+    camera: Camera2D,
+    /// The view as it was made, which following and zooming leave alone.
+    screen: Camera2D,
+    /// The zoom that shows the whole display rect, which `zoom` is relative to.
+    base_zoom: Vec2,
+    zoom: f32,
+    shake: Option<Shake>,
+    /// Only scale the view up in whole multiples when presenting it.
+    integer_scale: bool,
+}
+
+impl From<Camera2D> for Camera {
+    fn from(camera: Camera2D) -> Self {
+        Self {
+            camera,
+            screen: camera,
+            base_zoom: camera.zoom,
+            zoom: 1.0,
+            shake: None,
+            integer_scale: false,
+        }
+    }
+}
+
+impl Camera {
+    /// A camera showing exactly `view`, rendered offscreen at one texel per unit so `present` can
+    /// scale it up without distorting it.
+    pub fn new(view: Rect) -> Self {
+        let target = render_target(view.w as u32, view.h as u32);
+        target.texture.set_filter(FilterMode::Nearest);
+        let mut camera = Camera2D::from_display_rect(view);
+        camera.render_target = Some(target);
+        camera.into()
+    }
+
+    /// A copy of this camera with any shake stopped.
+    pub fn settled(&self) -> Self {
+        Self {
+            shake: None,
+            ..self.clone()
+        }
+    }
+
+    /// The camera to draw the world through, including any shake.
+    pub fn world(&self) -> Camera2D {
+        let mut camera = self.camera;
+        if let Some(shake) = &self.shake {
+            camera.target += shake.offset();
+        }
+        camera
+    }
+
+    /// The camera without shake, for things that should line up with the world but not jitter.
+    pub fn steady(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    /// The unmoved view, for things that stay put on the screen like dialogue and menus.
+    pub fn screen(&self) -> &Camera2D {
+        &self.screen
+    }
+
+    /// Centres the view on `target`.
+    pub fn follow(&mut self, target: Vec2) {
+        self.camera.target = target;
+    }
+
+    pub fn pan(&mut self, delta: Vec2) {
+        self.camera.target += delta;
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom;
+        self.camera.zoom = self.base_zoom * zoom;
+    }
+
+    /// Size of the view in world units.
+    pub fn view_size(&self) -> Vec2 {
+        2.0 / self.camera.zoom.abs()
+    }
+
+    /// Keeps the view inside `bounds`, centring it on any axis where `bounds` is the smaller.
+    pub fn clamp(&mut self, bounds: Rect) {
+        let view = types::Rect::from_center(self.camera.target, self.view_size())
+            .clamp_inside(&bounds.into());
+        let center = view.center();
+        self.camera.target = vec2(center.x, center.y);
+    }
+
+    /// Shakes the view by up to `intensity` pixels, settling over `duration` seconds.
+    pub fn shake(&mut self, intensity: f32, duration: f32) {
+        self.shake = Some(Shake {
+            intensity,
+            duration,
+            remaining: duration,
+        });
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        if let Some(shake) = &mut self.shake {
+            shake.remaining -= dt;
+            if shake.remaining <= 0.0 {
+                self.shake = None;
+            }
+        }
+    }
+
+    pub fn set_integer_scale(&mut self, integer_scale: bool) {
+        self.integer_scale = integer_scale;
+    }
+
+    /// Where the view lands on the screen: as large as fits without changing its aspect ratio,
+    /// centred, with the rest of the window left as bars.
+    fn screen_rect(&self) -> Rect {
+        let screen = vec2(screen_width(), screen_height());
+        let target = match self.camera.render_target {
+            Some(target) => target,
+            None => return Rect::new(0.0, 0.0, screen.x, screen.y),
+        };
+        let view = vec2(target.texture.width(), target.texture.height());
+        let mut scale = (screen.x / view.x).min(screen.y / view.y);
+        if self.integer_scale {
+            // A window smaller than the view still gets something rather than nothing
+            scale = scale.floor().max(1.0);
+        }
+        let size = view * scale;
+        let pos = ((screen - size) / 2.0).round();
+        Rect::new(pos.x, pos.y, size.x, size.y)
+    }
+
+    /// Draws everything rendered through this camera onto the screen.
+    pub fn present(&self) {
+        let target = match self.camera.render_target {
+            Some(target) => target,
+            None => return,
+        };
+        let rect = self.screen_rect();
+        set_default_camera();
+        draw_texture_ex(
+            target.texture,
+            rect.x,
+            rect.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(rect.size()),
+                // Render targets come out upside down
+                flip_y: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    pub fn screen_to_world(&self, point: Vec2) -> Vec2 {
+        let rect = self.screen_rect();
+        let ndc = vec2(
+            (point.x - rect.x) / rect.w * 2.0 - 1.0,
+            1.0 - (point.y - rect.y) / rect.h * 2.0,
+        );
+        let world = self
+            .camera
+            .matrix()
+            .inverse()
+            .transform_point3(ndc.extend(0.0));
+        vec2(world.x, world.y)
+    }
+
+    pub fn world_to_screen(&self, point: Vec2) -> Vec2 {
+        let rect = self.screen_rect();
+        let ndc = self.camera.matrix().transform_point3(point.extend(0.0));
+        vec2(
+            rect.x + (ndc.x + 1.0) / 2.0 * rect.w,
+            rect.y + (1.0 - ndc.y) / 2.0 * rect.h,
+        )
+    }
+}
@@ -4,3 +4,4 @@ use macroquad::color_u8;
 pub const LIGHT: Color = Color::new(215.0 / 255.0, 123.0 / 255.0, 186.0 / 255.0, 255.0 / 255.0);
 pub const DARK: Color = Color::new(34.0 / 255.0, 32.0 / 255.0, 52.0 / 255.0, 255.0 / 255.0);
 pub const BLUE: Color = color_u8!(99., 155., 255., 255.);
+pub const DIM: Color = Color::new(215.0 / 255.0, 123.0 / 255.0, 186.0 / 255.0, 100.0 / 255.0);
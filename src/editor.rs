@@ -1,19 +1,55 @@
 use crate::{
-    assets::Assets, colors, AnimationComponent, CollisionComponent, FollowComponent, Interactable,
-    Overworld, Position, SpriteComponent,
+    assets::{AnimatedSpriteId, Assets, TextureId},
+    camera::Camera,
+    colors, import, normalize_rect,
+    ustr::{ustr, Ustr},
+    AnimationComponent, AutoCollectComponent, CollisionComponent, FadeComponent, FollowComponent,
+    HealthComponent, Interactable, InteractableType, Inventory, ItemPickup, MovementComponent,
+    Name, Overworld, ParentComponent, Position, ShadowComponent, ShapeStyle, SpriteComponent,
+    TilemapComponent,
 };
 use hecs::{
-    serialize::row::{try_serialize, DeserializeContext, SerializeContext},
+    serialize::row::{DeserializeContext, SerializeContext},
     Entity, EntityBuilder, EntityRef, World,
 };
 use macroquad::prelude::*;
-use serde::{ser::SerializeStruct, Deserialize, Serialize};
-use std::{cell::RefCell, ops::DerefMut};
+use serde::{
+    de::{MapAccess, Visitor},
+    ser::{SerializeMap, SerializeStruct},
+    Deserialize, Serialize,
+};
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::{BTreeSet, HashMap, HashSet},
+    ops::DerefMut,
+    path::Path,
+};
+
+const PREFAB_DIR: &str = "assets/prefabs";
+const MAP_PATH: &str = "assets/overworld.json";
+const GRID_SIZE: f32 = 16.0;
+/// Tilemaps the paint tool starts on empty ground are this many cells square.
+const TILEMAP_CHUNK: usize = 16;
+/// The layer those tilemaps go on, just below the default sprite layer.
+const NEW_TILEMAP_LAYER: i32 = -1;
+const SNAP_DISTANCE: f32 = 4.0;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 8.0;
+const SCREENSHOT_PATH: &str = "map.png";
+/// Larger maps are scaled down so neither side of the screenshot exceeds this.
+const MAX_SCREENSHOT_SIZE: f32 = 4096.0;
+const COLLISIONS_PATH: &str = "collisions.json";
+
+fn ctrl_down() -> bool {
+    is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl)
+}
 
 enum Tool {
     Select,
     Move,
     Spawn,
+    Paint,
 }
 
 impl Default for Tool {
@@ -22,15 +58,32 @@ impl Default for Tool {
     }
 }
 
+impl Tool {
+    fn name(&self) -> &str {
+        match self {
+            Tool::Select => "Select (Q)",
+            Tool::Move => "Move (W)",
+            Tool::Spawn => "Spawn (E)",
+            Tool::Paint => "Paint (T)",
+        }
+    }
+}
+
 fn rect_manual_input_ui(ui: &mut egui::Ui, rect: &mut Rect) -> egui::Response {
-    ui.horizontal(|ui| {
-        ui.add(egui::DragValue::new(&mut rect.x)) | ui.add(egui::DragValue::new(&mut rect.y))
-    })
-    .inner
+    let response = ui
+        .horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut rect.x)) | ui.add(egui::DragValue::new(&mut rect.y))
+        })
+        .inner
         | ui.horizontal(|ui| {
             ui.add(egui::DragValue::new(&mut rect.w)) | ui.add(egui::DragValue::new(&mut rect.h))
         })
-        .inner
+        .inner;
+    // Wait until the drag ends, or dragging through zero would flip the rect under the cursor
+    if !response.dragged() {
+        *rect = normalize_rect(*rect);
+    }
+    response
 }
 
 fn vec2_manual_input_ui(ui: &mut egui::Ui, vec: &mut Vec2) -> egui::Response {
@@ -40,10 +93,15 @@ fn vec2_manual_input_ui(ui: &mut egui::Ui, vec: &mut Vec2) -> egui::Response {
     .inner
 }
 
-fn collisions_ui(ui: &mut egui::Ui, entity: EntityRef) {
+fn collisions_ui(ui: &mut egui::Ui, assets: &Assets, entity: EntityRef) {
     if let Some(mut col) = entity.get_mut::<CollisionComponent>() {
         ui.label("Collision rect:");
         rect_manual_input_ui(ui, &mut col.bounds);
+        if let Some(sprite) = entity.get::<SpriteComponent>() {
+            if ui.button("Fit collision to sprite").clicked() {
+                col.bounds = sprite.bounds(assets);
+            }
+        }
     }
 }
 
@@ -53,11 +111,160 @@ fn interactable_ui(ui: &mut egui::Ui, entity: EntityRef, builder: &mut EntityBui
         rect_manual_input_ui(ui, &mut int.bounds);
         ui.label("Priority:");
         ui.add(egui::DragValue::new(&mut int.priority));
+        ui.label("Range:");
+        ui.add(egui::DragValue::new(&mut int.range).clamp_range(0.0..=f32::MAX));
+        interaction_type_ui(ui, entity.entity(), &mut int.interaction);
     } else if ui.button("Add interaction").clicked() {
         builder.add(Interactable::default());
     }
 }
 
+fn interaction_type_ui(ui: &mut egui::Ui, entity: Entity, interaction: &mut InteractableType) {
+    let choices = [
+        InteractableType::Lamp,
+        InteractableType::Ghost,
+        InteractableType::Pickup,
+        InteractableType::Sign(String::new()),
+        InteractableType::Chest {
+            item: ustr(""),
+            opened: false,
+        },
+        InteractableType::Door {
+            target_map: MAP_PATH.to_owned(),
+            target_pos: Vec2::ZERO,
+        },
+    ];
+    egui::ComboBox::from_label("Type")
+        .selected_text(interaction.name())
+        .show_ui(ui, |ui| {
+            for choice in choices {
+                let selected = choice.name() == interaction.name();
+                if ui.selectable_label(selected, choice.name()).clicked() && !selected {
+                    *interaction = choice;
+                }
+            }
+        });
+    match interaction {
+        InteractableType::Sign(text) => {
+            ui.label("Text:");
+            ui.text_edit_multiline(text);
+        }
+        InteractableType::Chest { item, opened } => {
+            ui.label("Item:");
+            ustr_edit(ui, (entity, "chest item"), item);
+            ui.checkbox(opened, "Opened");
+        }
+        InteractableType::Door {
+            target_map,
+            target_pos,
+        } => {
+            ui.label("Target map:");
+            ui.text_edit_singleline(target_map);
+            ui.label("Target position:");
+            vec2_manual_input_ui(ui, target_pos);
+        }
+        _ => {}
+    }
+}
+
+/// A text field for a `Ustr`. Interned strings are never freed, so the text is edited as a
+/// scratch `String` and only interned once the field loses focus, not on every keystroke.
+fn ustr_edit(
+    ui: &mut egui::Ui,
+    id_source: impl std::hash::Hash + std::fmt::Debug,
+    value: &mut Ustr,
+) {
+    let id = ui.make_persistent_id(id_source);
+    let mut text = ui
+        .memory()
+        .id_data_temp
+        .get::<String>(&id)
+        .cloned()
+        .unwrap_or_else(|| value.to_string());
+    let response = ui.add(egui::TextEdit::singleline(&mut text).id(id));
+    if response.lost_focus() {
+        *value = ustr(&text);
+        ui.memory().id_data_temp.remove(&id);
+    } else if response.has_focus() {
+        ui.memory().id_data_temp.insert(id, text);
+    }
+}
+
+fn pickup_ui(ui: &mut egui::Ui, entity: EntityRef, builder: &mut EntityBuilder) {
+    if let Some(mut pickup) = entity.get_mut::<ItemPickup>() {
+        ui.label("Item:");
+        ustr_edit(ui, (entity.entity(), "pickup item"), &mut pickup.item);
+        ui.label("Count:");
+        ui.add(egui::DragValue::new(&mut pickup.count));
+        if let Some(mut auto_collect) = entity.get_mut::<AutoCollectComponent>() {
+            ui.label("Auto-collect radius:");
+            ui.add(egui::DragValue::new(&mut auto_collect.radius).clamp_range(0.0..=f32::MAX));
+        } else if ui.button("Auto-collect").clicked() {
+            builder.add(AutoCollectComponent::default());
+        }
+    } else if ui.button("Add item pickup").clicked() {
+        builder.add(ItemPickup::default());
+        builder.add(Interactable {
+            interaction: InteractableType::Pickup,
+            ..Default::default()
+        });
+    }
+}
+
+fn shadow_ui(ui: &mut egui::Ui, entity: EntityRef, builder: &mut EntityBuilder) {
+    if let Some(mut shadow) = entity.get_mut::<ShadowComponent>() {
+        ui.label("Shadow size:");
+        ui.add(
+            egui::DragValue::new(&mut shadow.size)
+                .speed(0.01)
+                .clamp_range(0.0..=4.0),
+        );
+        ui.label("Shadow alpha:");
+        ui.add(
+            egui::DragValue::new(&mut shadow.alpha)
+                .speed(0.01)
+                .clamp_range(0.0..=1.0),
+        );
+    } else if ui.button("Add shadow").clicked() {
+        builder.add(ShadowComponent::default());
+    }
+}
+
+fn movement_ui(ui: &mut egui::Ui, entity: EntityRef) {
+    if let Some(mut movement) = entity.get_mut::<MovementComponent>() {
+        ui.label("Walking speed:");
+        ui.add(
+            egui::DragValue::new(&mut movement.speed)
+                .speed(0.05)
+                .clamp_range(0.0..=8.0),
+        );
+    }
+}
+
+/// Returns whether the user asked to pick a follow target in the viewport.
+fn follow_ui(ui: &mut egui::Ui, entity: EntityRef) -> bool {
+    if let Some(mut follow) = entity.get_mut::<FollowComponent>() {
+        ui.label(format!("Following: {}", follow.target.id()));
+        ui.label("Max distance:");
+        ui.add(egui::DragValue::new(&mut follow.max_distance));
+        ui.label("Speed:");
+        ui.add(egui::DragValue::new(&mut follow.speed).speed(0.1));
+        ui.checkbox(&mut follow.snap_to_axis, "Snap to axis");
+        ui.button("Pick target").clicked()
+    } else {
+        ui.button("Follow...").clicked()
+    }
+}
+
+fn name_ui(ui: &mut egui::Ui, entity: EntityRef, builder: &mut EntityBuilder) {
+    if let Some(mut name) = entity.get_mut::<Name>() {
+        ui.label("Name:");
+        ustr_edit(ui, (entity.entity(), "name"), &mut name.0);
+    } else if ui.button("Add name").clicked() {
+        builder.add(Name(ustr("unnamed")));
+    }
+}
+
 fn position_ui(ui: &mut egui::Ui, entity: EntityRef) {
     if let Some(mut pos) = entity.get_mut::<Position>() {
         vec2_manual_input_ui(ui, &mut pos.0);
@@ -69,6 +276,7 @@ fn sprite_ui(ui: &mut egui::Ui, entity: EntityRef) {
         ui.label("Offset");
         vec2_manual_input_ui(ui, &mut sprite.offset);
         ui.checkbox(&mut sprite.centered, "Centered");
+        ui.checkbox(&mut sprite.flip_h, "Flip horizontally");
         if let Some(source) = &mut sprite.source {
             ui.label("Source:");
             rect_manual_input_ui(ui, source);
@@ -79,6 +287,14 @@ fn sprite_ui(ui: &mut egui::Ui, entity: EntityRef) {
         }
         ui.label("Layer:");
         ui.add(egui::DragValue::new(&mut sprite.layer));
+        ui.label("Sort offset:");
+        ui.add(egui::DragValue::new(&mut sprite.sort_offset));
+        ui.label("Alpha:");
+        ui.add(
+            egui::DragValue::new(&mut sprite.alpha)
+                .speed(0.01)
+                .clamp_range(0.0..=1.0),
+        );
     }
 }
 
@@ -86,8 +302,18 @@ fn animation_ui(ui: &mut egui::Ui, entity: EntityRef) {
     if let Some(mut animation) = entity.get_mut::<AnimationComponent>() {
         ui.label("Offset:");
         vec2_manual_input_ui(ui, &mut animation.offset);
-        ui.label("Frame:");
-        ui.add(egui::DragValue::new(&mut animation.frame));
+        ui.label("Frame (Ctrl+wheel to scrub):");
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut animation.frame));
+            ui.checkbox(&mut animation.paused, "Paused");
+        });
+        ui.label("Speed:");
+        ui.add(
+            egui::DragValue::new(&mut animation.speed)
+                .speed(0.05)
+                .clamp_range(0.0..=8.0),
+        );
+        ui.checkbox(&mut animation.notify_loops, "Notify on loop");
     }
 }
 
@@ -96,31 +322,88 @@ impl Serialize for Overworld {
     where
         S: serde::Serializer,
     {
-        let mut context = OverworldSerializeContext;
+        let context = OverworldSerializeContext::for_world(&self.world);
         let mut state = serializer.serialize_struct("Overworld", 2)?;
-        state.serialize_field("player", &self.player)?;
-        state.serialize_field(
-            "world",
-            &SerializeWorld(RefCell::new((&mut context, &self.world))),
-        )?;
+        state.serialize_field("player", &context.stable_id(self.player))?;
+        state.serialize_field("world", &SerializeWorld(RefCell::new(context), &self.world))?;
         state.end()
     }
 }
 
-struct SerializeWorld<'a, C>(RefCell<(&'a mut C, &'a World)>)
-where
-    C: SerializeContext;
+/// Entities are keyed by their stable id rather than their raw bits, which depend on how the
+/// world got to its current state.
+struct SerializeWorld<'a>(RefCell<OverworldSerializeContext>, &'a World);
 
-impl<'a, C> Serialize for SerializeWorld<'a, C>
-where
-    C: SerializeContext,
-{
+impl<'a> Serialize for SerializeWorld<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut context = self.0.borrow_mut();
+        let mut map = serializer.serialize_map(Some(self.1.len() as usize))?;
+        for entity in self.1.iter() {
+            map.serialize_key(&context.stable_id(entity.entity()))?;
+            map.serialize_value(&SerializeComponents(RefCell::new(&mut context), entity))?;
+        }
+        map.end()
+    }
+}
+
+struct SerializeComponents<'a, 'b>(RefCell<&'a mut OverworldSerializeContext>, EntityRef<'b>);
+
+impl<'a, 'b> Serialize for SerializeComponents<'a, 'b> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        let mut this = self.0.borrow_mut();
-        hecs::serialize::row::serialize(this.1, this.0, serializer)
+        let mut map = serializer.serialize_map(None)?;
+        self.0.borrow_mut().serialize_entity(self.1, &mut map)?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Overworld {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct SavedOverworld {
+            player: Entity,
+            #[serde(deserialize_with = "deserialize_world")]
+            world: (World, HashMap<Entity, Entity>),
+        }
+
+        let SavedOverworld {
+            player,
+            world: (world, ids),
+        } = SavedOverworld::deserialize(deserializer)?;
+        let mut overworld = Overworld {
+            world,
+            player: remap(&ids, player),
+            ticks: 0,
+        };
+        overworld.break_follow_loops();
+        Ok(overworld)
+    }
+}
+
+/// Stands in for references to entities that weren't part of the saved world.
+fn dangling() -> Entity {
+    Entity::from_bits(u64::MAX)
+}
+
+fn remap(ids: &HashMap<Entity, Entity>, entity: Entity) -> Entity {
+    ids.get(&entity).copied().unwrap_or_else(dangling)
+}
+
+/// Rewrites the entity references held by `component`. Any component that stores an `Entity`
+/// needs a case here, or its links won't survive a save and load.
+fn map_entity_refs(component: &mut dyn Any, mut map: impl FnMut(Entity) -> Entity) {
+    if let Some(follow) = component.downcast_mut::<FollowComponent>() {
+        follow.target = map(follow.target);
+    } else if let Some(parent) = component.downcast_mut::<ParentComponent>() {
+        parent.parent = map(parent.parent);
     }
 }
 
@@ -133,6 +416,16 @@ macro_rules! apply_component_ids {
             Animation : AnimationComponent,
             Interaction : Interactable,
             Follow: FollowComponent,
+            Tilemap: TilemapComponent,
+            Inventory: Inventory,
+            Pickup: ItemPickup,
+            Parent: ParentComponent,
+            Health: HealthComponent,
+            AutoCollect: AutoCollectComponent,
+            Name: Name,
+            Shadow: ShadowComponent,
+            Movement: MovementComponent,
+            Fade: FadeComponent,
         }
     };
 }
@@ -150,6 +443,66 @@ fn duplicate_entity(entity: EntityRef, builder: &mut EntityBuilder) {
     //     builder.add((*component).clone());
     // }
 }
+
+/// A toggle per known component, lit if the entity has it. Returns the one clicked and whether
+/// the entity had it.
+fn components_ui(ui: &mut egui::Ui, entity: EntityRef) -> Option<(ComponentId, bool)> {
+    let mut clicked = None;
+    ui.horizontal_wrapped(|ui| {
+        macro_rules! badge_helper {
+            ($($id:ident : $ty:ty,)*) => {
+                $(let present = entity.get::<$ty>().is_some();
+                if ui.selectable_label(present, stringify!($id)).clicked() {
+                    clicked = Some((ComponentId::$id, present));
+                })*
+            };
+        }
+        apply_component_ids!(badge_helper);
+    });
+    clicked
+}
+
+/// Adds a fresh `id` component, for the ones that make sense without any setup. Ones that need
+/// something chosen first, like a parent or a tileset, are left alone.
+fn add_default_component(id: ComponentId, builder: &mut EntityBuilder) {
+    match id {
+        ComponentId::Position => builder.add(Position(Vec2::ZERO)),
+        ComponentId::Sprite => builder.add(SpriteComponent::default()),
+        ComponentId::Collision => builder.add(CollisionComponent {
+            bounds: Rect::new(0.0, 0.0, GRID_SIZE, GRID_SIZE),
+        }),
+        ComponentId::Animation => builder.add(AnimationComponent::default()),
+        ComponentId::Interaction => builder.add(Interactable::default()),
+        ComponentId::Inventory => builder.add(Inventory::default()),
+        ComponentId::Pickup => builder.add(ItemPickup::default()),
+        ComponentId::Health => builder.add(HealthComponent {
+            health: 3,
+            max_health: 3,
+        }),
+        ComponentId::AutoCollect => builder.add(AutoCollectComponent::default()),
+        ComponentId::Name => builder.add(Name(ustr("unnamed"))),
+        ComponentId::Shadow => builder.add(ShadowComponent::default()),
+        ComponentId::Movement => builder.add(MovementComponent::default()),
+        ComponentId::Fade => builder.add(FadeComponent::default()),
+        ComponentId::Follow | ComponentId::Tilemap | ComponentId::Parent => {
+            println!("That component can't be added from here");
+            builder
+        }
+    };
+}
+
+fn remove_component(world: &mut World, entity: Entity, id: ComponentId) {
+    macro_rules! remove_helper {
+        ($($id:ident : $ty:ty,)*) => {
+            match id {
+                $(ComponentId::$id => {
+                    world.remove_one::<$ty>(entity).ok();
+                })*
+            }
+        };
+    }
+    apply_component_ids!(remove_helper);
+}
 struct OverworldDeserializeContext;
 
 impl DeserializeContext for OverworldDeserializeContext {
@@ -181,15 +534,47 @@ impl DeserializeContext for OverworldDeserializeContext {
     }
 }
 
-pub fn deserialize_world<'de, D>(deserializer: D) -> Result<World, D::Error>
+/// Spawns every saved entity fresh, then makes a second pass to point references at the new
+/// entities. Returns the mapping from saved ids so the caller can remap its own references.
+fn deserialize_world<'de, D>(deserializer: D) -> Result<(World, HashMap<Entity, Entity>), D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    let mut context = OverworldDeserializeContext;
-    hecs::serialize::row::deserialize(&mut context, deserializer)
+    deserializer.deserialize_map(WorldVisitor)
+}
+
+struct WorldVisitor;
+
+impl<'de> Visitor<'de> for WorldVisitor {
+    type Value = (World, HashMap<Entity, Entity>);
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a world")
+    }
+
+    fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut world = World::new();
+        let mut ids = HashMap::new();
+        while let Some(id) = map.next_key::<Entity>()? {
+            let Prefab(mut builder) = map.next_value()?;
+            ids.insert(id, world.spawn(builder.build()));
+        }
+        macro_rules! remap_helper {
+            ($($id:ident : $ty:ty,)*) => {
+                $(for (_, component) in world.query_mut::<&mut $ty>() {
+                    map_entity_refs(component, |entity| remap(&ids, entity));
+                })*
+            };
+        }
+        apply_component_ids!(remap_helper);
+        Ok((world, ids))
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 enum ComponentId {
     Position,
     Sprite,
@@ -197,9 +582,38 @@ enum ComponentId {
     Animation,
     Interaction,
     Follow,
+    Tilemap,
+    Inventory,
+    Pickup,
+    Parent,
+    Health,
+    AutoCollect,
+    Name,
+    Shadow,
+    Movement,
+    Fade,
+}
+
+/// Hands out stable ids, numbered in iteration order, for the entities being saved.
+#[derive(Default)]
+struct OverworldSerializeContext {
+    ids: HashMap<Entity, Entity>,
 }
 
-struct OverworldSerializeContext;
+impl OverworldSerializeContext {
+    fn for_world(world: &World) -> Self {
+        let ids = world
+            .iter()
+            .enumerate()
+            .map(|(i, entity)| (entity.entity(), Entity::from_bits(i as u64)))
+            .collect();
+        Self { ids }
+    }
+
+    fn stable_id(&self, entity: Entity) -> Entity {
+        remap(&self.ids, entity)
+    }
+}
 
 impl SerializeContext for OverworldSerializeContext {
     fn serialize_entity<S>(&mut self, entity: EntityRef<'_>, map: &mut S) -> Result<(), S::Error>
@@ -208,7 +622,11 @@ impl SerializeContext for OverworldSerializeContext {
     {
         macro_rules! serialize_helper {
             ($($id:ident : $ty:ty,)*) => {
-                $(try_serialize::<$ty, _, _>(&entity, &ComponentId::$id, map)?;)*
+                $(if let Some(component) = entity.get::<$ty>() {
+                    let mut component = (*component).clone();
+                    map_entity_refs(&mut component, |entity| self.stable_id(entity));
+                    map.serialize_entry(&ComponentId::$id, &component)?;
+                })*
             }
         }
         apply_component_ids!(serialize_helper);
@@ -221,59 +639,566 @@ impl SerializeContext for OverworldSerializeContext {
     }
 }
 
+struct SerializePrefab<'a>(EntityRef<'a>);
+
+impl<'a> Serialize for SerializePrefab<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        OverworldSerializeContext::default().serialize_entity(self.0, &mut map)?;
+        map.end()
+    }
+}
+
+struct Prefab(EntityBuilder);
+
+impl<'de> Deserialize<'de> for Prefab {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(PrefabVisitor)
+    }
+}
+
+struct PrefabVisitor;
+
+impl<'de> Visitor<'de> for PrefabVisitor {
+    type Value = Prefab;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a map of components")
+    }
+
+    fn visit_map<M>(self, map: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut builder = EntityBuilder::new();
+        OverworldDeserializeContext.deserialize_entity(map, &mut builder)?;
+        Ok(Prefab(builder))
+    }
+}
+
+/// Prefabs are kept as raw JSON so each instance can be deserialized into a fresh builder.
+struct PrefabEntry {
+    name: String,
+    data: serde_json::Value,
+}
+
+fn save_prefab(name: &str, entity: EntityRef) -> anyhow::Result<()> {
+    // The name becomes the file name, so it mustn't reach outside the prefab folder
+    if name.contains(['/', '\\']) {
+        anyhow::bail!("Prefab name {:?} can't contain path separators", name);
+    }
+    std::fs::create_dir_all(PREFAB_DIR)?;
+    let file = std::fs::File::create(Path::new(PREFAB_DIR).join(format!("{}.json", name)))?;
+    serde_json::to_writer_pretty(file, &SerializePrefab(entity))?;
+    Ok(())
+}
+
+fn load_prefabs() -> anyhow::Result<Vec<PrefabEntry>> {
+    let mut prefabs = Vec::new();
+    if !Path::new(PREFAB_DIR).exists() {
+        return Ok(prefabs);
+    }
+    for entry in std::fs::read_dir(PREFAB_DIR)? {
+        let path = entry?.path();
+        if path.extension() != Some("json".as_ref()) {
+            continue;
+        }
+        let name = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(name) => name.to_owned(),
+            None => continue,
+        };
+        let data = serde_json::from_reader(std::fs::File::open(&path)?)?;
+        prefabs.push(PrefabEntry { name, data });
+    }
+    prefabs.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(prefabs)
+}
+
+fn spawn_prefab(
+    overworld: &mut Overworld,
+    prefab: &PrefabEntry,
+    pos: Vec2,
+) -> anyhow::Result<Entity> {
+    let Prefab(mut builder) = Prefab::deserialize(&prefab.data)?;
+    builder.add(Position(pos));
+    Ok(overworld.world.spawn(builder.build()))
+}
+
+/// A camera owned by the editor, so the map can be panned and zoomed without disturbing the
+/// game's view.
+struct EditorCamera {
+    camera: Camera,
+    grab: Option<Vec2>,
+}
+
+impl EditorCamera {
+    fn new(camera: &Camera) -> Self {
+        Self {
+            // Start from the game's view, but without any shake it happens to be in
+            camera: camera.settled(),
+            grab: None,
+        }
+    }
+
+    fn cursor(&self) -> Vec2 {
+        self.camera.screen_to_world(Vec2::from(mouse_position()))
+    }
+
+    fn update(&mut self) {
+        if is_mouse_button_pressed(MouseButton::Middle) {
+            self.grab = Some(self.cursor());
+        }
+        if !is_mouse_button_down(MouseButton::Middle) {
+            self.grab = None;
+        }
+        if let Some(grab) = self.grab {
+            // Keep the grabbed point under the cursor
+            self.camera.pan(grab - self.cursor());
+        }
+
+        let (_, wheel) = mouse_wheel();
+        // Ctrl+wheel is left for scrubbing animations
+        if wheel != 0.0 && !ctrl_down() {
+            let before = self.cursor();
+            let factor = if wheel > 0.0 { 1.25 } else { 0.8 };
+            let zoom = (self.camera.zoom() * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+            self.camera.set_zoom(zoom);
+            // Zoom around the cursor rather than the centre of the screen
+            self.camera.pan(before - self.cursor());
+        }
+    }
+}
+
+/// What the spawn tool places.
+#[derive(Clone, Copy, Default, PartialEq)]
+enum SpawnChoice {
+    #[default]
+    ConceptArt,
+    /// Index into `OverworldEditor::prefabs`.
+    Prefab(usize),
+    AnimatedSprite(AnimatedSpriteId),
+}
+
+/// Spawns an entity showing `id`, already playing the sheet's first animation.
+fn spawn_animated_sprite(
+    assets: &Assets,
+    overworld: &mut Overworld,
+    id: AnimatedSpriteId,
+    pos: Vec2,
+) -> Entity {
+    let animation = assets.get(&id).first_animation().unwrap_or("Idle");
+    overworld.world.spawn((
+        Position(pos),
+        SpriteComponent {
+            texture: TextureId::AnimatedSpriteId(id),
+            centered: true,
+            ..Default::default()
+        },
+        AnimationComponent {
+            id,
+            animation: ustr(animation),
+            ..Default::default()
+        },
+    ))
+}
+
 #[derive(Default)]
 pub struct OverworldEditor {
     tool: Tool,
     selected: Option<Entity>,
     drag: Option<(Entity, Vec2)>,
-    show_collisions: bool,
+    collision_style: Option<ShapeStyle>,
+    compact_save: bool,
+    prefab_name: String,
+    prefabs: Vec<PrefabEntry>,
+    /// Whether `prefabs` has been read from disk yet. That happens the first time the editor
+    /// updates.
+    prefabs_loaded: bool,
+    spawn: SpawnChoice,
+    import_path: String,
+    camera: Option<EditorCamera>,
+    hidden_layers: HashSet<i32>,
+    locked_layers: HashSet<i32>,
+    pixel_perfect_picking: bool,
+    /// Also pick entities that have no sprite, by their collision or interaction box.
+    pick_invisibles: bool,
+    snap_to_edges: bool,
+    map_path: Option<String>,
+    brush_tile: u32,
+    /// Texture for tilemaps the paint tool starts where there isn't one to paint into.
+    brush_tileset: Option<Ustr>,
+    /// The entity whose follow target will be set by the next click in the viewport.
+    picking_follow_target: Option<Entity>,
+    /// The last cell painted or erased during the current stroke.
+    painted_cell: Option<(Entity, usize)>,
+    /// Paint with the tileset's autotiling set instead of `brush_tile`.
+    autotile: bool,
 }
 
 impl OverworldEditor {
+    /// Save and load from `path` instead of the default map.
+    pub fn set_map_path(&mut self, path: String) {
+        self.map_path = Some(path);
+    }
+
+    fn map_path(&self) -> &str {
+        self.map_path.as_deref().unwrap_or(MAP_PATH)
+    }
+
     fn save(&self, overworld: &Overworld) -> anyhow::Result<()> {
-        // let mut output = Vec::with_capacity(128);
-        // let mut serializer = serde_json::Serializer::pretty(&mut output);
-        // hecs::serialize::row::serialize(
-        //     &overworld.world,
-        //     &mut OverworldSerializeContext {},
-        //     &mut serializer,
-        // )?;
-        // println!(
-        //     "{}",
-        //     std::str::from_utf8(output.as_slice()).unwrap_or("UTF8 error")
-        // );
-        let file = std::fs::File::create("assets/overworld.json")?;
-        serde_json::to_writer(file, overworld)?;
-        // println!("{}", serde_json::to_string_pretty(overworld)?);
+        let file = std::fs::File::create(self.map_path())?;
+        if self.compact_save {
+            serde_json::to_writer(file, overworld)?;
+        } else {
+            serde_json::to_writer_pretty(file, overworld)?;
+        }
         Ok(())
     }
 
+    /// Reads the map without touching the one being played or edited.
+    pub async fn read_map(&self) -> anyhow::Result<Overworld> {
+        Ok(serde_json::from_slice(&load_file(self.map_path()).await?)?)
+    }
+
     pub async fn load(&self, overworld: &mut Overworld) -> anyhow::Result<()> {
-        *overworld = serde_json::from_slice(&load_file("assets/overworld.json").await?)?;
+        *overworld = self.read_map().await?;
         Ok(())
     }
 
-    fn highlight_hovered(&self, assets: &Assets, overworld: &mut Overworld, camera: &Camera2D) {
-        let cursor = camera.screen_to_world(Vec2::from(mouse_position()));
+    fn delete_selected(&mut self, overworld: &mut Overworld) {
+        if let Some(entity) = self.selected.take() {
+            // The entity may already be gone if the map was reloaded underneath us
+            overworld.world.despawn(entity).ok();
+        }
+    }
+
+    /// Left-drag paints the brush tile into the topmost editable tilemap under the cursor and
+    /// right-drag erases, touching each cell once per stroke.
+    fn paint(&mut self, assets: &Assets, overworld: &mut Overworld, cursor: Vec2) {
+        let painting = is_mouse_button_down(MouseButton::Left);
+        let erasing = is_mouse_button_down(MouseButton::Right);
+        if !painting && !erasing {
+            self.painted_cell = None;
+            return;
+        }
+        if painting && self.paint_target(overworld, cursor).is_none() {
+            self.spawn_tilemap_chunk(assets, overworld, cursor);
+        }
+        if let Some((entity, tilemap, cell)) = self.paint_target(overworld, cursor) {
+            if self.painted_cell == Some((entity, cell)) {
+                return;
+            }
+            let autotile_set = match assets.autotile_set(tilemap.tileset) {
+                Some(set) if self.autotile => Some(set),
+                _ => None,
+            };
+            tilemap.tiles[cell] = match (painting, autotile_set) {
+                // Any tile from the set will do; `autotile` picks the right one
+                (true, Some(set)) => Some(set[0]),
+                (true, None) => Some(self.brush_tile),
+                (false, _) => None,
+            };
+            if let Some(set) = autotile_set {
+                tilemap.autotile(cell, set);
+            }
+            self.painted_cell = Some((entity, cell));
+        }
+    }
+
+    /// The cell under `cursor` in the topmost tilemap that can be painted.
+    fn paint_target<'a>(
+        &self,
+        overworld: &'a mut Overworld,
+        cursor: Vec2,
+    ) -> Option<(Entity, &'a mut TilemapComponent, usize)> {
+        overworld
+            .world
+            .query_mut::<(&Position, &mut TilemapComponent)>()
+            .into_iter()
+            .filter(|(_, (_, tilemap))| {
+                !self.hidden_layers.contains(&tilemap.layer)
+                    && !self.locked_layers.contains(&tilemap.layer)
+            })
+            .filter_map(|(entity, (Position(pos), tilemap))| {
+                let cell = tilemap.cell_at(*pos, cursor)?;
+                Some((entity, tilemap, cell))
+            })
+            .max_by_key(|(_, tilemap, _)| tilemap.layer)
+    }
+
+    /// Starts an empty tilemap of the brush's tileset under `cursor`, aligned to whole chunks so
+    /// neighbouring ones butt up against each other.
+    fn spawn_tilemap_chunk(&self, assets: &Assets, overworld: &mut Overworld, cursor: Vec2) {
+        let tileset = match self.brush_tileset {
+            Some(name) => assets.get_texture(name),
+            None => return,
+        };
+        if self.hidden_layers.contains(&NEW_TILEMAP_LAYER)
+            || self.locked_layers.contains(&NEW_TILEMAP_LAYER)
+        {
+            return;
+        }
+        let chunk_size = GRID_SIZE * TILEMAP_CHUNK as f32;
+        overworld.world.spawn((
+            Position((cursor / chunk_size).floor() * chunk_size),
+            TilemapComponent {
+                tileset,
+                tile_size: vec2(GRID_SIZE, GRID_SIZE),
+                width: TILEMAP_CHUNK,
+                tiles: vec![None; TILEMAP_CHUNK * TILEMAP_CHUNK],
+                layer: NEW_TILEMAP_LAYER,
+            },
+        ));
+    }
+
+    /// Ctrl+wheel steps the selected entity's animation a frame at a time, pausing it so the
+    /// pose sticks.
+    fn scrub_animation(&self, assets: &Assets, overworld: &mut Overworld) {
+        let (_, wheel) = mouse_wheel();
+        if wheel == 0.0 || !ctrl_down() {
+            return;
+        }
+        let entity = match self.selected {
+            Some(entity) => entity,
+            None => return,
+        };
+        if let Ok(animation) = overworld
+            .world
+            .query_one_mut::<&mut AnimationComponent>(entity)
+        {
+            let length = assets
+                .get(&animation.id)
+                .get_anim_length(animation.animation.as_str());
+            if length == 0 {
+                return;
+            }
+            animation.paused = true;
+            animation.frame = if wheel > 0.0 {
+                (animation.frame + 1) % length
+            } else {
+                (animation.frame + length - 1) % length
+            };
+        }
+    }
 
-        if let Some((entity, _)) = overworld.query_cursor_pos(assets, cursor) {
-            if let Ok((Position(pos), sprite)) = overworld
+    /// Points `follower` at whatever is clicked, adding a FollowComponent if it doesn't have one.
+    fn pick_follow_target(
+        &mut self,
+        assets: &Assets,
+        overworld: &mut Overworld,
+        follower: Entity,
+        cursor: Vec2,
+    ) {
+        if is_key_pressed(KeyCode::Escape) {
+            self.picking_follow_target = None;
+            return;
+        }
+        if !is_mouse_button_pressed(MouseButton::Left) {
+            return;
+        }
+        self.picking_follow_target = None;
+        let target = match self.pick(assets, overworld, cursor) {
+            Some((target, _)) => target,
+            None => return,
+        };
+        if overworld.follow_loops(follower, target) {
+            println!(
+                "Can't follow {}: it would end up following itself",
+                target.id()
+            );
+            return;
+        }
+        if let Ok(follow) = overworld
+            .world
+            .query_one_mut::<&mut FollowComponent>(follower)
+        {
+            follow.target = target;
+        } else {
+            overworld
                 .world
-                .query_one_mut::<(&Position, &SpriteComponent)>(entity)
+                .insert_one(
+                    follower,
+                    FollowComponent {
+                        target,
+                        max_distance: 32.0,
+                        speed: 1.0,
+                        snap_to_axis: false,
+                    },
+                )
+                .ok();
+        }
+    }
+
+    fn nudge(&self, overworld: &mut Overworld, entity: Entity) {
+        let step = if is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift) {
+            GRID_SIZE
+        } else {
+            1.0
+        };
+        let mut delta = Vec2::ZERO;
+        if is_key_pressed(KeyCode::Up) {
+            delta.y -= step;
+        }
+        if is_key_pressed(KeyCode::Down) {
+            delta.y += step;
+        }
+        if is_key_pressed(KeyCode::Left) {
+            delta.x -= step;
+        }
+        if is_key_pressed(KeyCode::Right) {
+            delta.x += step;
+        }
+        if delta != Vec2::ZERO {
+            if let Ok(Position(pos)) = overworld.world.query_one_mut::<&mut Position>(entity) {
+                *pos += delta;
+            }
+        }
+    }
+
+    /// The camera the map should be drawn with while the editor is open.
+    pub fn camera(&self) -> Option<&Camera2D> {
+        self.camera.as_ref().map(|camera| camera.camera.steady())
+    }
+
+    pub fn hidden_layers(&self) -> &HashSet<i32> {
+        &self.hidden_layers
+    }
+
+    /// Picks the topmost entity under the cursor, skipping hidden and locked layers.
+    fn pick(&self, assets: &Assets, overworld: &Overworld, cursor: Vec2) -> Option<(Entity, Vec2)> {
+        let ignored_layers: HashSet<i32> = self
+            .hidden_layers
+            .union(&self.locked_layers)
+            .cloned()
+            .collect();
+        overworld.query_cursor_pos(
+            assets,
+            cursor,
+            &ignored_layers,
+            self.pixel_perfect_picking,
+            self.pick_invisibles,
+        )
+    }
+
+    /// Moves `entity` so its sprite sits flush with, or lines up with, any nearby sprite edge.
+    /// Holding Alt places freely.
+    fn snap(&self, assets: &Assets, overworld: &mut Overworld, entity: Entity) {
+        if !self.snap_to_edges || is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt) {
+            return;
+        }
+        let bounds = match overworld
+            .world
+            .query_one_mut::<(&Position, &SpriteComponent)>(entity)
+        {
+            Ok((Position(pos), sprite)) => sprite.bounds(assets).offset(*pos),
+            Err(_) => return,
+        };
+
+        let closest = |best: Option<f32>, candidate: f32| match best {
+            Some(best) if best.abs() <= candidate.abs() => Some(best),
+            _ if candidate.abs() <= SNAP_DISTANCE => Some(candidate),
+            _ => best,
+        };
+        let mut snap_x = None;
+        let mut snap_y = None;
+        for (other, (Position(other_pos), sprite)) in overworld
+            .world
+            .query::<(&Position, &SpriteComponent)>()
+            .iter()
+        {
+            if other == entity
+                || self.hidden_layers.contains(&sprite.layer)
+                || self.locked_layers.contains(&sprite.layer)
             {
-                let bounds = sprite.bounds(assets).offset(*pos);
+                continue;
+            }
+            let other_bounds = sprite.bounds(assets).offset(*other_pos);
+            let near_vertically = bounds.top() <= other_bounds.bottom() + SNAP_DISTANCE
+                && bounds.bottom() >= other_bounds.top() - SNAP_DISTANCE;
+            let near_horizontally = bounds.left() <= other_bounds.right() + SNAP_DISTANCE
+                && bounds.right() >= other_bounds.left() - SNAP_DISTANCE;
+            if near_vertically {
+                for candidate in [
+                    other_bounds.left() - bounds.right(),
+                    other_bounds.right() - bounds.left(),
+                    other_bounds.left() - bounds.left(),
+                    other_bounds.right() - bounds.right(),
+                ] {
+                    snap_x = closest(snap_x, candidate);
+                }
+            }
+            if near_horizontally {
+                for candidate in [
+                    other_bounds.top() - bounds.bottom(),
+                    other_bounds.bottom() - bounds.top(),
+                    other_bounds.top() - bounds.top(),
+                    other_bounds.bottom() - bounds.bottom(),
+                ] {
+                    snap_y = closest(snap_y, candidate);
+                }
+            }
+        }
+
+        if let Ok(Position(pos)) = overworld.world.query_one_mut::<&mut Position>(entity) {
+            *pos += vec2(snap_x.unwrap_or(0.0), snap_y.unwrap_or(0.0));
+        }
+    }
+
+    fn layers_ui(&mut self, ui: &mut egui::Ui, overworld: &Overworld) {
+        let mut layers: BTreeSet<i32> = overworld
+            .world
+            .query::<&SpriteComponent>()
+            .iter()
+            .map(|(_, sprite)| sprite.layer)
+            .collect();
+        layers.extend(
+            overworld
+                .world
+                .query::<&TilemapComponent>()
+                .iter()
+                .map(|(_, tilemap)| tilemap.layer),
+        );
+        for layer in layers {
+            ui.horizontal(|ui| {
+                ui.label(format!("Layer {}", layer));
+                let mut visible = !self.hidden_layers.contains(&layer);
+                if ui.checkbox(&mut visible, "Visible").changed() {
+                    if visible {
+                        self.hidden_layers.remove(&layer);
+                    } else {
+                        self.hidden_layers.insert(layer);
+                    }
+                }
+                let mut locked = self.locked_layers.contains(&layer);
+                if ui.checkbox(&mut locked, "Locked").changed() {
+                    if locked {
+                        self.locked_layers.insert(layer);
+                    } else {
+                        self.locked_layers.remove(&layer);
+                    }
+                }
+            });
+        }
+    }
+
+    fn highlight_hovered(&self, assets: &Assets, overworld: &Overworld, cursor: Vec2) {
+        if let Some((entity, _)) = self.pick(assets, overworld, cursor) {
+            if let Some(bounds) = overworld.visible_bounds(assets, entity) {
                 draw_rectangle_lines(bounds.x, bounds.y, bounds.w, bounds.h, 1.0, colors::LIGHT);
             }
         }
     }
 
-    fn highlight_selected(&self, assets: &Assets, overworld: &mut Overworld) {
+    fn highlight_selected(&self, assets: &Assets, overworld: &Overworld) {
         if let Some(entity) = self.selected {
-            if let Ok((Position(pos), sprite)) = overworld
-                .world
-                .query_one_mut::<(&Position, &SpriteComponent)>(entity)
-            {
-                let bounds = sprite.bounds(assets).offset(*pos);
+            let pos = overworld.world.get::<Position>(entity).map(|pos| pos.0);
+            if let (Ok(pos), Some(bounds)) = (pos, overworld.visible_bounds(assets, entity)) {
                 draw_rectangle_lines(bounds.x, bounds.y, bounds.w, bounds.h, 1.0, colors::LIGHT);
 
                 let crosshair_size = 10.0;
@@ -298,37 +1223,177 @@ impl OverworldEditor {
         }
     }
 
+    /// Renders the whole map, not just the viewport, to an offscreen target and saves it as a PNG.
+    fn export_screenshot(&self, assets: &Assets, overworld: &Overworld) -> anyhow::Result<()> {
+        let bounds = overworld
+            .bounds(assets)
+            .ok_or_else(|| anyhow::anyhow!("the map is empty"))?;
+        let scale = (MAX_SCREENSHOT_SIZE / bounds.w.max(bounds.h)).min(1.0);
+        let target = render_target(
+            (bounds.w * scale).ceil() as u32,
+            (bounds.h * scale).ceil() as u32,
+        );
+        target.texture.set_filter(FilterMode::Nearest);
+        let mut camera = Camera2D::from_display_rect(bounds);
+        camera.render_target = Some(target);
+        set_camera(&camera);
+        clear_background(colors::DARK);
+        overworld.draw(assets, &self.hidden_layers);
+        // Switching cameras flushes the draw calls into the target
+        set_default_camera();
+        target
+            .texture
+            .get_texture_data()
+            .export_png(SCREENSHOT_PATH);
+        target.delete();
+        Ok(())
+    }
+
+    /// Writes every collision box, in world space, as a flat list for tools that only care
+    /// about colliders. The map file is untouched.
+    fn export_collisions(&self, overworld: &Overworld) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Collider {
+            entity: u32,
+            x: f32,
+            y: f32,
+            w: f32,
+            h: f32,
+        }
+
+        let colliders: Vec<Collider> = overworld
+            .world
+            .query::<(&Position, &CollisionComponent)>()
+            .iter()
+            .map(|(entity, (Position(pos), collision))| {
+                let bounds = collision.bounds.offset(*pos);
+                Collider {
+                    entity: entity.id(),
+                    x: bounds.x,
+                    y: bounds.y,
+                    w: bounds.w,
+                    h: bounds.h,
+                }
+            })
+            .collect();
+        let file = std::fs::File::create(COLLISIONS_PATH)?;
+        serde_json::to_writer_pretty(file, &colliders)?;
+        Ok(())
+    }
+
     pub async fn update(&mut self, assets: &Assets, game: &crate::Game) {
         let mut game = game.0.borrow_mut();
         let crate::_Game {
             overworld, camera, ..
         } = game.deref_mut();
+        let editor_camera = self.camera.get_or_insert_with(|| EditorCamera::new(camera));
+        set_camera(editor_camera.camera.steady());
         let mut should_load = false;
+        let mut should_reload_prefabs = !self.prefabs_loaded;
+        let mut should_import = false;
+        let mut should_import_ldtk = false;
+        let mut should_export = false;
         egui_macroquad::ui(|egui_ctx| {
-            egui::Window::new("hi!")
+            egui::Window::new("Editor")
                 .resizable(true)
                 .show(egui_ctx, |ui| {
-                    ui.label("Test");
+                    ui.label(format!("Tool: {}", self.tool.name()));
+                    ui.label(format!("Entities: {}", overworld.world.len()));
+                    if self.picking_follow_target.is_some() {
+                        ui.label("Click an entity to follow (Esc to cancel)");
+                    }
+                    if let Tool::Paint = self.tool {
+                        ui.horizontal(|ui| {
+                            ui.label("Brush tile:");
+                            ui.add(egui::DragValue::new(&mut self.brush_tile));
+                        });
+                        ui.checkbox(&mut self.autotile, "Autotile")
+                            .on_hover_text("Uses the tileset's autotile set from the manifest");
+                        egui::ComboBox::from_label("New tilemap tileset")
+                            .selected_text(
+                                self.brush_tileset.map_or("(none)", |name| name.as_str()),
+                            )
+                            .show_ui(ui, |ui| {
+                                for name in assets.texture_names() {
+                                    ui.selectable_value(
+                                        &mut self.brush_tileset,
+                                        Some(name),
+                                        name.as_str(),
+                                    );
+                                }
+                            });
+                        ui.label("Left-drag to paint, right-drag to erase");
+                    }
+                    ui.label(format!(
+                        "Edge snapping: {}",
+                        if self.snap_to_edges {
+                            "on (Alt to bypass)"
+                        } else {
+                            "off"
+                        }
+                    ));
+                    match self.selected {
+                        Some(entity) => ui.label(format!("Selected: {}", entity.id())),
+                        None => ui.label("Selected: none"),
+                    };
+                    ui.separator();
                     if let Some(entity) = self.selected {
                         if ui.button("Delete").clicked() {
-                            overworld.world.despawn(entity).unwrap();
+                            self.delete_selected(overworld);
                         }
                         let mut builder = EntityBuilder::new();
+                        let mut removed = None;
                         if let Ok(entity_ref) = overworld.world.entity(entity) {
+                            match components_ui(ui, entity_ref) {
+                                Some((id, true)) => removed = Some(id),
+                                Some((ComponentId::Follow, false)) => {
+                                    self.picking_follow_target = Some(entity);
+                                }
+                                Some((id, false)) => add_default_component(id, &mut builder),
+                                None => {}
+                            }
+                            ui.separator();
+                            name_ui(ui, entity_ref, &mut builder);
                             position_ui(ui, entity_ref);
                             sprite_ui(ui, entity_ref);
                             animation_ui(ui, entity_ref);
-                            collisions_ui(ui, entity_ref);
+                            shadow_ui(ui, entity_ref, &mut builder);
+                            collisions_ui(ui, assets, entity_ref);
                             interactable_ui(ui, entity_ref, &mut builder);
+                            pickup_ui(ui, entity_ref, &mut builder);
+                            movement_ui(ui, entity_ref);
+                            if follow_ui(ui, entity_ref) {
+                                self.picking_follow_target = Some(entity);
+                            }
+                            ui.horizontal(|ui| {
+                                ui.text_edit_singleline(&mut self.prefab_name);
+                                if ui.button("Save as prefab").clicked()
+                                    && !self.prefab_name.is_empty()
+                                {
+                                    match save_prefab(&self.prefab_name, entity_ref) {
+                                        Ok(()) => should_reload_prefabs = true,
+                                        Err(e) => println!("Failed to save prefab: {}", e),
+                                    }
+                                }
+                            });
                             if ui.button("Duplicate").clicked() {
                                 let mut builder = EntityBuilder::new();
                                 duplicate_entity(entity_ref, &mut builder);
-                                overworld.world.spawn(builder.build());
+                                let copy = overworld.world.spawn(builder.build());
+                                if let Ok(Position(pos)) =
+                                    overworld.world.query_one_mut::<&mut Position>(copy)
+                                {
+                                    *pos += vec2(GRID_SIZE, GRID_SIZE);
+                                }
+                                self.selected = Some(copy);
                             }
                         }
                         if builder.component_types().next().is_some() {
                             overworld.world.insert(entity, builder.build()).unwrap();
                         }
+                        if let Some(id) = removed {
+                            remove_component(&mut overworld.world, entity, id);
+                        }
                     }
                     if ui.button("Spawn new thing").clicked() {
                         for pos in overworld
@@ -345,11 +1410,15 @@ impl OverworldEditor {
                                     flip_h: false,
                                     layer: -1,
                                     centered: false,
+                                    tint: WHITE,
+                                    sort_offset: 0.0,
+                                    alpha: 1.0,
                                 },
                             ));
                         }
                     }
 
+                    ui.checkbox(&mut self.compact_save, "Compact save");
                     if ui.button("Save").clicked() {
                         self.save(overworld)
                             .unwrap_or_else(|e| println!("Failed to save: {}", e));
@@ -358,8 +1427,93 @@ impl OverworldEditor {
                     if ui.button("Load").clicked() {
                         should_load = true;
                     }
+
+                    if ui.button("Export screenshot").clicked() {
+                        should_export = true;
+                    }
+
+                    if ui.button("Export collisions").clicked() {
+                        self.export_collisions(overworld)
+                            .unwrap_or_else(|e| println!("Failed to export collisions: {}", e));
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut self.import_path);
+                        if ui.button("Import Tiled map").clicked() {
+                            should_import = true;
+                        }
+                        if ui.button("Import LDtk level").clicked() {
+                            should_import_ldtk = true;
+                        }
+                    });
+
+                    ui.separator();
+                    ui.label("Prefabs:");
+                    if ui
+                        .selectable_label(self.spawn == SpawnChoice::ConceptArt, "(concept art)")
+                        .clicked()
+                    {
+                        self.spawn = SpawnChoice::ConceptArt;
+                    }
+                    for (i, prefab) in self.prefabs.iter().enumerate() {
+                        if ui
+                            .selectable_label(self.spawn == SpawnChoice::Prefab(i), &prefab.name)
+                            .clicked()
+                        {
+                            self.spawn = SpawnChoice::Prefab(i);
+                            self.tool = Tool::Spawn;
+                        }
+                    }
+                    ui.label("Animated sprites:");
+                    for id in assets.animated_sprite_ids() {
+                        let choice = SpawnChoice::AnimatedSprite(id);
+                        if ui
+                            .selectable_label(self.spawn == choice, id.name().as_str())
+                            .clicked()
+                        {
+                            self.spawn = choice;
+                            self.tool = Tool::Spawn;
+                        }
+                    }
+                    if ui.button("Refresh prefabs").clicked() {
+                        should_reload_prefabs = true;
+                    }
+
+                    ui.checkbox(&mut self.pixel_perfect_picking, "Pixel-perfect picking");
+                    ui.checkbox(&mut self.pick_invisibles, "Pick invisibles");
+                    ui.checkbox(&mut self.snap_to_edges, "Snap to edges");
+
+                    ui.separator();
+                    ui.label("Layers:");
+                    self.layers_ui(ui, overworld);
                 });
 
+            if should_reload_prefabs {
+                self.prefabs_loaded = true;
+                match load_prefabs() {
+                    Ok(prefabs) => {
+                        self.prefabs = prefabs;
+                        if let SpawnChoice::Prefab(_) = self.spawn {
+                            self.spawn = SpawnChoice::ConceptArt;
+                        }
+                    }
+                    Err(e) => println!("Failed to load prefabs: {}", e),
+                }
+            }
+
+            // Label the selection with its name, since names are how scripts find entities
+            if let (Some(camera), Some(entity)) = (&self.camera, self.selected) {
+                if let Ok((Position(pos), Name(name))) =
+                    overworld.world.query_one_mut::<(&Position, &Name)>(entity)
+                {
+                    let screen = camera.camera.world_to_screen(*pos) / egui_ctx.pixels_per_point();
+                    egui::Area::new("selected_name")
+                        .fixed_pos(egui::pos2(screen.x, screen.y))
+                        .interactable(false)
+                        .show(egui_ctx, |ui| ui.label(name.as_str()));
+                }
+            }
+
             if !egui_ctx.wants_keyboard_input() {
                 if is_key_pressed(KeyCode::Q) {
                     self.tool = Tool::Select;
@@ -370,71 +1524,125 @@ impl OverworldEditor {
                 if is_key_pressed(KeyCode::E) {
                     self.tool = Tool::Spawn;
                 }
+                if is_key_pressed(KeyCode::T) {
+                    self.tool = Tool::Paint;
+                }
                 if is_key_pressed(KeyCode::H) {
-                    self.show_collisions = !self.show_collisions;
+                    // Cycle filled -> outline -> off
+                    self.collision_style = match self.collision_style {
+                        None => Some(ShapeStyle::Filled),
+                        Some(ShapeStyle::Filled) => Some(ShapeStyle::Outline),
+                        Some(ShapeStyle::Outline) => None,
+                    };
+                }
+                if let Some(entity) = self.selected {
+                    self.nudge(overworld, entity);
+                }
+                if is_key_pressed(KeyCode::Delete) || is_key_pressed(KeyCode::Backspace) {
+                    self.delete_selected(overworld);
                 }
             }
 
-            if self.show_collisions {
-                overworld.draw_collisions();
-                overworld.draw_interactions();
+            if let Some(style) = self.collision_style {
+                overworld.draw_collisions(style);
+                overworld.draw_interactions(style);
             }
 
             self.highlight_selected(assets, overworld);
 
             if !egui_ctx.wants_pointer_input() {
-                let cursor = camera.screen_to_world(Vec2::from(mouse_position()));
-                match self.tool {
-                    Tool::Select => {
-                        self.highlight_hovered(assets, overworld, camera);
-                        if is_mouse_button_pressed(MouseButton::Left) {
-                            self.selected = overworld
-                                .query_cursor_pos(assets, cursor)
-                                .map(|(entity, _)| entity);
-                        }
-                    }
-                    Tool::Move => {
-                        self.highlight_hovered(assets, overworld, camera);
-                        if is_mouse_button_pressed(MouseButton::Left) {
-                            self.drag = overworld.query_cursor_pos(assets, cursor);
+                self.scrub_animation(assets, overworld);
+                let editor_camera = self.camera.as_mut().unwrap();
+                editor_camera.update();
+                let cursor = editor_camera.cursor();
+                if let Some(follower) = self.picking_follow_target {
+                    self.pick_follow_target(assets, overworld, follower, cursor);
+                } else {
+                    match self.tool {
+                        Tool::Select => {
+                            self.highlight_hovered(assets, overworld, cursor);
+                            if is_mouse_button_pressed(MouseButton::Left) {
+                                self.selected = self
+                                    .pick(assets, overworld, cursor)
+                                    .map(|(entity, _)| entity);
+                            }
                         }
+                        Tool::Move => {
+                            self.highlight_hovered(assets, overworld, cursor);
+                            if is_mouse_button_pressed(MouseButton::Left) {
+                                self.drag = self.pick(assets, overworld, cursor);
+                            }
 
-                        if is_mouse_button_down(MouseButton::Left) {
-                            if let Some((drag, offset)) = self.drag {
-                                let cursor = camera.screen_to_world(Vec2::from(mouse_position()));
-                                if let Ok(pos) =
-                                    overworld.world.query_one_mut::<&mut Position>(drag)
-                                {
-                                    *pos = Position(Vec2::new(cursor.x, cursor.y) + offset);
+                            if is_mouse_button_down(MouseButton::Left) {
+                                if let Some((drag, offset)) = self.drag {
+                                    if let Ok(pos) =
+                                        overworld.world.query_one_mut::<&mut Position>(drag)
+                                    {
+                                        *pos = Position(Vec2::new(cursor.x, cursor.y) + offset);
+                                    }
+                                    self.snap(assets, overworld, drag);
                                 }
                             }
                         }
-                    }
-                    Tool::Spawn => {
-                        if is_mouse_button_pressed(MouseButton::Left) {
-                            overworld.world.spawn((
-                                Position(cursor),
-                                SpriteComponent {
-                                    texture: assets.char_concept,
-                                    source: None,
-                                    offset: Default::default(),
-                                    flip_h: false,
-                                    layer: -1,
-                                    centered: false,
-                                },
-                            ));
+                        Tool::Spawn => {
+                            if is_mouse_button_pressed(MouseButton::Left) {
+                                let spawned = match self.spawn {
+                                    SpawnChoice::Prefab(i) => {
+                                        self.prefabs.get(i).and_then(|prefab| {
+                                            spawn_prefab(overworld, prefab, cursor)
+                                                .map_err(|e| {
+                                                    println!("Failed to spawn prefab: {}", e)
+                                                })
+                                                .ok()
+                                        })
+                                    }
+                                    SpawnChoice::AnimatedSprite(id) => {
+                                        Some(spawn_animated_sprite(assets, overworld, id, cursor))
+                                    }
+                                    SpawnChoice::ConceptArt => Some(overworld.world.spawn((
+                                        Position(cursor),
+                                        SpriteComponent {
+                                            texture: assets.char_concept,
+                                            source: None,
+                                            offset: Default::default(),
+                                            flip_h: false,
+                                            layer: -1,
+                                            centered: false,
+                                            tint: WHITE,
+                                            sort_offset: 0.0,
+                                            alpha: 1.0,
+                                        },
+                                    ))),
+                                };
+                                if let Some(spawned) = spawned {
+                                    self.snap(assets, overworld, spawned);
+                                }
+                            }
                         }
+                        Tool::Paint => self.paint(assets, overworld, cursor),
                     }
                 }
             }
         });
 
-        set_default_camera();
-        egui_macroquad::draw();
+        if should_export {
+            self.export_screenshot(assets, overworld)
+                .unwrap_or_else(|e| println!("Failed to export screenshot: {}", e));
+        }
         if should_load {
             self.load(overworld)
                 .await
                 .unwrap_or_else(|e| println!("Failed to load: {}", e));
         }
+        if should_import {
+            import::import_tiled(overworld, assets, Path::new(&self.import_path))
+                .await
+                .unwrap_or_else(|e| println!("Failed to import Tiled map: {}", e));
+        }
+        if should_import_ldtk {
+            import::import_ldtk(overworld, assets, Path::new(&self.import_path))
+                .await
+                .unwrap_or_else(|e| println!("Failed to import LDtk level: {}", e));
+        }
     }
 }
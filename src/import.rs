@@ -0,0 +1,5 @@
+mod ldtk;
+mod tiled;
+
+pub use ldtk::import_ldtk;
+pub use tiled::import_tiled;
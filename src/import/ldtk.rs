@@ -0,0 +1,251 @@
+use crate::{
+    assets::Assets, CollisionComponent, Interactable, InteractableType, Overworld, Position,
+    TilemapComponent,
+};
+use anyhow::{anyhow, bail};
+use hecs::Entity;
+use macroquad::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+mod deserialize {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    pub struct Project {
+        pub defs: Defs,
+        pub levels: Vec<Level>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Defs {
+        pub tilesets: Vec<Tileset>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Tileset {
+        pub uid: i64,
+        pub identifier: String,
+        pub rel_path: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Level {
+        pub identifier: String,
+        /// Missing when the project saves levels to separate files.
+        pub layer_instances: Option<Vec<LayerInstance>>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct LayerInstance {
+        #[serde(rename = "__type")]
+        pub kind: String,
+        #[serde(rename = "__gridSize")]
+        pub grid_size: f32,
+        #[serde(rename = "__cWid")]
+        pub c_wid: usize,
+        #[serde(rename = "__cHei")]
+        pub c_hei: usize,
+        #[serde(rename = "__pxTotalOffsetX")]
+        pub px_total_offset_x: f32,
+        #[serde(rename = "__pxTotalOffsetY")]
+        pub px_total_offset_y: f32,
+        #[serde(rename = "__tilesetDefUid")]
+        pub tileset_def_uid: Option<i64>,
+        #[serde(default)]
+        pub int_grid_csv: Vec<i32>,
+        #[serde(default)]
+        pub grid_tiles: Vec<Tile>,
+        #[serde(default)]
+        pub auto_layer_tiles: Vec<Tile>,
+        #[serde(default)]
+        pub entity_instances: Vec<EntityInstance>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Tile {
+        pub px: [f32; 2],
+        pub t: u32,
+    }
+
+    #[derive(Deserialize)]
+    pub struct EntityInstance {
+        #[serde(rename = "__identifier")]
+        pub identifier: String,
+        #[serde(rename = "__pivot")]
+        pub pivot: [f32; 2],
+        pub px: [f32; 2],
+        pub width: f32,
+        pub height: f32,
+    }
+}
+
+/// Replaces everything but the player in `overworld` with the first level of an LDtk project.
+///
+/// Layers with tiles become tilemaps, stacked below the sprite layers in the order LDtk draws
+/// them. Non-zero IntGrid cells become colliders, one per horizontal run. Entities are spawned
+/// by identifier the same way Tiled objects are: `Collision`, `Player`, or an `InteractableType`.
+pub async fn import_ldtk(
+    overworld: &mut Overworld,
+    assets: &Assets,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| anyhow!("Project path {} isn't valid UTF-8", path.display()))?;
+    let project: deserialize::Project = serde_json::from_str(&load_string(path_str).await?)?;
+    let level = project
+        .levels
+        .first()
+        .ok_or_else(|| anyhow!("The project has no levels"))?;
+    let layers = level.layer_instances.as_ref().ok_or_else(|| {
+        anyhow!(
+            "Level {} is saved in a separate file, which isn't supported",
+            level.identifier
+        )
+    })?;
+
+    let project_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let tileset_texture = |uid: i64| {
+        let tileset = project
+            .defs
+            .tilesets
+            .iter()
+            .find(|tileset| tileset.uid == uid)
+            .ok_or_else(|| anyhow!("No tileset with uid {}", uid))?;
+        let image = tileset
+            .rel_path
+            .as_ref()
+            .ok_or_else(|| anyhow!("Tileset {} has no image", tileset.identifier))?;
+        assets
+            .find_texture_by_path(&project_dir.join(image))
+            .ok_or_else(|| {
+                anyhow!(
+                    "No texture in the asset manifest for tileset image {}",
+                    image
+                )
+            })
+    };
+
+    // Everything that can fail is checked before the overworld is touched, so a bad project
+    // leaves the current map as it was
+    let mut tilesets = HashMap::new();
+    for layer in layers {
+        match layer.kind.as_str() {
+            "IntGrid" | "Tiles" | "AutoLayer" | "Entities" => {}
+            other => bail!("Unsupported layer type: {}", other),
+        }
+        if let Some(uid) = layer.tileset_def_uid {
+            tilesets.insert(uid, tileset_texture(uid)?);
+        }
+    }
+
+    let stale: Vec<Entity> = overworld
+        .world
+        .iter()
+        .map(|entity| entity.entity())
+        .filter(|&entity| entity != overworld.player)
+        .collect();
+    for entity in stale {
+        overworld.world.despawn(entity).unwrap();
+    }
+
+    // LDtk lists layers top first
+    let tile_layer_count = layers
+        .iter()
+        .filter(|layer| layer.tileset_def_uid.is_some())
+        .count() as i32;
+    let mut tile_layer_index = 0;
+
+    for layer in layers.iter().rev() {
+        let offset = vec2(layer.px_total_offset_x, layer.px_total_offset_y);
+        let tile_size = vec2(layer.grid_size, layer.grid_size);
+        if layer.kind == "Entities" {
+            for entity in &layer.entity_instances {
+                spawn_entity(overworld, entity, offset);
+            }
+            continue;
+        }
+
+        for (i, &value) in layer.int_grid_csv.iter().enumerate() {
+            let (x, y) = (i % layer.c_wid, i / layer.c_wid);
+            // Each run is spawned from its leftmost cell
+            let run_start = value != 0 && (x == 0 || layer.int_grid_csv[i - 1] == 0);
+            if !run_start {
+                continue;
+            }
+            let run = layer.int_grid_csv[i..i + layer.c_wid - x]
+                .iter()
+                .take_while(|&&value| value != 0)
+                .count();
+            overworld.world.spawn((
+                Position(offset + vec2(x as f32, y as f32) * tile_size),
+                CollisionComponent {
+                    bounds: Rect::new(0., 0., run as f32 * tile_size.x, tile_size.y),
+                },
+            ));
+        }
+
+        if let Some(uid) = layer.tileset_def_uid {
+            let mut tiles = vec![None; layer.c_wid * layer.c_hei];
+            for tile in layer.grid_tiles.iter().chain(&layer.auto_layer_tiles) {
+                let x = (tile.px[0] / layer.grid_size) as usize;
+                let y = (tile.px[1] / layer.grid_size) as usize;
+                // Only one tile fits in a cell; LDtk's later tiles draw on top, so they win
+                if x < layer.c_wid && y < layer.c_hei {
+                    tiles[y * layer.c_wid + x] = Some(tile.t);
+                }
+            }
+            overworld.world.spawn((
+                Position(offset),
+                TilemapComponent {
+                    tileset: tilesets[&uid],
+                    tile_size,
+                    width: layer.c_wid,
+                    tiles,
+                    layer: tile_layer_index - tile_layer_count,
+                },
+            ));
+            tile_layer_index += 1;
+        }
+    }
+    Ok(())
+}
+
+fn spawn_entity(overworld: &mut Overworld, entity: &deserialize::EntityInstance, offset: Vec2) {
+    let size = vec2(entity.width, entity.height);
+    // `px` is wherever the pivot is; components are placed by their top-left corner
+    let top_left = offset + Vec2::from(entity.px) - Vec2::from(entity.pivot) * size;
+    let pos = Position(top_left);
+    let bounds = Rect::new(0., 0., size.x, size.y);
+    match entity.identifier.as_str() {
+        "Collision" => {
+            overworld.world.spawn((pos, CollisionComponent { bounds }));
+        }
+        "Player" => {
+            if let Ok(player_pos) = overworld
+                .world
+                .query_one_mut::<&mut Position>(overworld.player)
+            {
+                *player_pos = pos;
+            }
+        }
+        other => match serde_json::from_value::<InteractableType>(other.into()) {
+            Ok(interaction) => {
+                overworld.world.spawn((
+                    pos,
+                    Interactable {
+                        bounds,
+                        interaction,
+                        priority: 0,
+                        range: 0.0,
+                    },
+                ));
+            }
+            Err(_) => println!("Skipping entity with unknown identifier {:?}", other),
+        },
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{
+    assets::{Assets, TextureId},
+    CollisionComponent, Interactable, InteractableType, Overworld, Position, TilemapComponent,
+};
+use anyhow::{anyhow, bail};
+use hecs::Entity;
+use macroquad::prelude::*;
+use std::path::Path;
+
+/// Tiled stores flip/rotation flags in the top bits of each global tile id.
+const GID_MASK: u32 = 0x1FFF_FFFF;
+
+mod deserialize {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    pub struct Map {
+        pub orientation: String,
+        pub tilewidth: f32,
+        pub tileheight: f32,
+        pub layers: Vec<Layer>,
+        pub tilesets: Vec<Tileset>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(tag = "type", rename_all = "lowercase")]
+    pub enum Layer {
+        Tilelayer {
+            data: Vec<u32>,
+            width: usize,
+            #[serde(default)]
+            offsetx: f32,
+            #[serde(default)]
+            offsety: f32,
+        },
+        Objectgroup {
+            objects: Vec<Object>,
+        },
+        #[serde(other)]
+        Other,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Tileset {
+        pub firstgid: u32,
+        pub image: Option<String>,
+        #[serde(default)]
+        pub name: String,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Object {
+        pub x: f32,
+        pub y: f32,
+        #[serde(default)]
+        pub width: f32,
+        #[serde(default)]
+        pub height: f32,
+        #[serde(default, alias = "class")]
+        pub r#type: String,
+    }
+}
+
+/// Replaces everything but the player in `overworld` with the contents of a Tiled JSON map.
+///
+/// Each tile layer becomes one tilemap per tileset it uses, stacked below the sprite layers in
+/// the order Tiled draws them. Objects are spawned by type: `Collision` becomes a collider,
+/// `Player` moves the player, and anything naming an `InteractableType` becomes an interactable.
+pub async fn import_tiled(
+    overworld: &mut Overworld,
+    assets: &Assets,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| anyhow!("Map path {} isn't valid UTF-8", path.display()))?;
+    let map: deserialize::Map = serde_json::from_str(&load_string(path_str).await?)?;
+    if map.orientation != "orthogonal" {
+        bail!("Unsupported map orientation: {}", map.orientation);
+    }
+
+    let map_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut tilesets = Vec::with_capacity(map.tilesets.len());
+    for tileset in &map.tilesets {
+        let image = tileset.image.as_ref().ok_or_else(|| {
+            anyhow!(
+                "Tileset {} must be embedded in the map, external tilesets aren't supported",
+                tileset.name
+            )
+        })?;
+        let texture = assets
+            .find_texture_by_path(&map_dir.join(image))
+            .ok_or_else(|| {
+                anyhow!(
+                    "No texture in the asset manifest for tileset image {}",
+                    image
+                )
+            })?;
+        tilesets.push((tileset.firstgid, texture));
+    }
+    tilesets.sort_by_key(|(firstgid, _)| *firstgid);
+
+    // Everything that can fail is checked before the overworld is touched, so a bad map leaves
+    // the current one as it was
+    let mut layer_tilemaps = Vec::new();
+    for layer in &map.layers {
+        if let deserialize::Layer::Tilelayer { data, .. } = layer {
+            layer_tilemaps.push(split_by_tileset(data, &tilesets)?);
+        }
+    }
+
+    let stale: Vec<Entity> = overworld
+        .world
+        .iter()
+        .map(|entity| entity.entity())
+        .filter(|&entity| entity != overworld.player)
+        .collect();
+    for entity in stale {
+        overworld.world.despawn(entity).unwrap();
+    }
+
+    let tile_size = vec2(map.tilewidth, map.tileheight);
+    let tile_layer_count = map
+        .layers
+        .iter()
+        .filter(|layer| matches!(layer, deserialize::Layer::Tilelayer { .. }))
+        .count() as i32;
+    let mut tile_layer_index = 0;
+
+    for layer in &map.layers {
+        match layer {
+            deserialize::Layer::Tilelayer {
+                width,
+                offsetx,
+                offsety,
+                ..
+            } => {
+                let tilemaps = std::mem::take(&mut layer_tilemaps[tile_layer_index as usize]);
+                for (tileset, tiles) in tilemaps {
+                    overworld.world.spawn((
+                        Position(vec2(*offsetx, *offsety)),
+                        TilemapComponent {
+                            tileset: tilesets[tileset].1,
+                            tile_size,
+                            width: *width,
+                            tiles,
+                            layer: tile_layer_index - tile_layer_count,
+                        },
+                    ));
+                }
+                tile_layer_index += 1;
+            }
+            deserialize::Layer::Objectgroup { objects } => {
+                for object in objects {
+                    let pos = Position(vec2(object.x, object.y));
+                    let bounds = Rect::new(0., 0., object.width, object.height);
+                    match object.r#type.as_str() {
+                        "Collision" => {
+                            overworld.world.spawn((pos, CollisionComponent { bounds }));
+                        }
+                        "Player" => {
+                            if let Ok(player_pos) = overworld
+                                .world
+                                .query_one_mut::<&mut Position>(overworld.player)
+                            {
+                                *player_pos = pos;
+                            }
+                        }
+                        other => match serde_json::from_value::<InteractableType>(other.into()) {
+                            Ok(interaction) => {
+                                overworld.world.spawn((
+                                    pos,
+                                    Interactable {
+                                        bounds,
+                                        interaction,
+                                        priority: 0,
+                                        range: 0.0,
+                                    },
+                                ));
+                            }
+                            Err(_) => println!("Skipping object with unknown type {:?}", other),
+                        },
+                    }
+                }
+            }
+            deserialize::Layer::Other => {}
+        }
+    }
+    Ok(())
+}
+
+/// Splits a tile layer's gids into one set of tiles per tileset, as indices into `tilesets`
+/// with tile ids local to that tileset.
+fn split_by_tileset(
+    data: &[u32],
+    tilesets: &[(u32, TextureId)],
+) -> anyhow::Result<Vec<(usize, Vec<Option<u32>>)>> {
+    let mut tilemaps: Vec<(usize, Vec<Option<u32>>)> = Vec::new();
+    for (i, gid) in data.iter().enumerate() {
+        let gid = gid & GID_MASK;
+        if gid == 0 {
+            continue;
+        }
+        let tileset = match tilesets.iter().rposition(|(firstgid, _)| *firstgid <= gid) {
+            Some(tileset) => tileset,
+            None => bail!("Tile id {} doesn't belong to any tileset", gid),
+        };
+        let tiles = match tilemaps.iter().position(|(t, _)| *t == tileset) {
+            Some(index) => &mut tilemaps[index].1,
+            None => {
+                tilemaps.push((tileset, vec![None; data.len()]));
+                &mut tilemaps.last_mut().unwrap().1
+            }
+        };
+        tiles[i] = Some(gid - tilesets[tileset].0);
+    }
+    Ok(tilemaps)
+}
@@ -1,15 +1,19 @@
 #![feature(result_cloned)]
 #![feature(option_get_or_insert_default)]
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
+use std::task::Poll;
+use std::time::Duration;
 
-use bmfont::CharPosition;
+use bmfont::{BMFont, CharPosition};
 use colors::DARK;
 use futures::executor::LocalSpawner;
+use futures::future::{abortable, AbortHandle, LocalBoxFuture};
 use futures::task::LocalSpawnExt;
-use futures::Future;
-use hecs::{Entity, World};
+use futures::{Future, FutureExt};
+use hecs::{Component, Entity, EntityRef, World};
 use macroquad::prelude::*;
 
 use serde::Deserialize;
@@ -18,28 +22,120 @@ use serde_with::serde_as;
 use serde_with::{DeserializeAs, SerializeAs};
 
 mod assets;
+mod camera;
 mod colors;
 mod editor;
+mod import;
 mod types;
 mod ustr;
 
+use types::{Circle, Direction};
 use ustr::*;
 
-use assets::Assets;
-use assets::{AnimatedSpriteId, TextureId};
+use assets::{AnimatedSpriteId, FontId, TextureId};
+use assets::{Assets, LoadProgress};
+use camera::Camera;
 
-use editor::{deserialize_world, OverworldEditor};
+use editor::OverworldEditor;
 
 // fn main() {
 //     println!("Hello, world!");
 // }
 
+const CONFIG_PATH: &str = "assets/config.json";
+
+/// Window settings read from `assets/config.json`. Missing fields keep the defaults below.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct WindowConfig {
+    title: String,
+    width: i32,
+    height: i32,
+    fullscreen: bool,
+    resizable: bool,
+    high_dpi: bool,
+    sample_count: i32,
+    /// Only scale the game up in whole multiples, so every pixel is the same size.
+    integer_scale: bool,
+    text_speed: TextSpeed,
+}
+
+/// How quickly dialogue types out.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+enum TextSpeed {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+}
+
+impl TextSpeed {
+    fn chars_per_tick(self) -> f32 {
+        match self {
+            TextSpeed::Slow => DIALOGUE_CHARS_PER_TICK / 2.0,
+            TextSpeed::Normal => DIALOGUE_CHARS_PER_TICK,
+            TextSpeed::Fast => DIALOGUE_CHARS_PER_TICK * 2.0,
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            TextSpeed::Slow => TextSpeed::Normal,
+            TextSpeed::Normal => TextSpeed::Fast,
+            TextSpeed::Fast => TextSpeed::Slow,
+        }
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "safe".to_owned(),
+            width: 1280,
+            height: 720,
+            fullscreen: false,
+            resizable: false,
+            high_dpi: false,
+            sample_count: 1,
+            integer_scale: false,
+            text_speed: TextSpeed::Normal,
+        }
+    }
+}
+
+impl WindowConfig {
+    fn save(&self) -> anyhow::Result<()> {
+        let file = std::fs::File::create(CONFIG_PATH)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    // Conf has to exist before the async runtime starts, so this can't go through load_string
+    fn load() -> Self {
+        let text = match std::fs::read_to_string(CONFIG_PATH) {
+            Ok(text) => text,
+            Err(_) => return Self::default(),
+        };
+        match serde_json::from_str(&text) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("Failed to parse {}: {}", CONFIG_PATH, e);
+                Self::default()
+            }
+        }
+    }
+}
+
 fn window_conf() -> Conf {
+    let config = WindowConfig::load();
     Conf {
-        window_title: "safe".to_owned(),
-        window_width: 1280,
-        window_height: 720,
-        window_resizable: false,
+        window_title: config.title,
+        window_width: config.width,
+        window_height: config.height,
+        fullscreen: config.fullscreen,
+        window_resizable: config.resizable,
+        high_dpi: config.high_dpi,
+        sample_count: config.sample_count,
         ..Default::default()
     }
 }
@@ -69,22 +165,134 @@ impl<'de> DeserializeAs<'de, Rect> for RectDef {
     }
 }
 
+/// Flips negative widths and heights so that `overlaps` and `contains` behave.
+fn normalize_rect(rect: Rect) -> Rect {
+    types::Rect::from(rect).normalize().into()
+}
+
+/// Like `RectDef`, but normalizes on load so hand-edited files can't break collision.
+struct NormalizedRectDef;
+impl SerializeAs<Rect> for NormalizedRectDef {
+    fn serialize_as<S>(source: &Rect, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        RectDef::serialize(source, serializer)
+    }
+}
+impl<'de> DeserializeAs<'de, Rect> for NormalizedRectDef {
+    fn deserialize_as<D>(deserializer: D) -> Result<Rect, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        RectDef::deserialize(deserializer).map(normalize_rect)
+    }
+}
+
+/// Writes a `Vec2` as `[x, y]` ourselves, so saved maps don't depend on how macroquad's math
+/// library chooses to serialize it.
+struct Vec2Def;
+impl SerializeAs<Vec2> for Vec2Def {
+    fn serialize_as<S>(source: &Vec2, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        (source.x, source.y).serialize(serializer)
+    }
+}
+impl<'de> DeserializeAs<'de, Vec2> for Vec2Def {
+    fn deserialize_as<D>(deserializer: D) -> Result<Vec2, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let (x, y) = <(f32, f32)>::deserialize(deserializer)?;
+        Ok(vec2(x, y))
+    }
+}
+
+/// Writes a `Color` as `[r, g, b, a]`, for the same reason as `Vec2Def`.
+struct ColorDef;
+impl SerializeAs<Color> for ColorDef {
+    fn serialize_as<S>(source: &Color, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        (source.r, source.g, source.b, source.a).serialize(serializer)
+    }
+}
+impl<'de> DeserializeAs<'de, Color> for ColorDef {
+    fn deserialize_as<D>(deserializer: D) -> Result<Color, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let (r, g, b, a) = <(f32, f32, f32, f32)>::deserialize(deserializer)?;
+        Ok(Color::new(r, g, b, a))
+    }
+}
+
+#[serde_as]
 #[derive(Clone, Copy, Serialize, Deserialize)]
-struct Position(Vec2);
+struct Position(#[serde_as(as = "Vec2Def")] Vec2);
 
 #[serde_as]
-#[derive(Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 struct SpriteComponent {
     texture: TextureId,
     #[serde_as(as = "Option<RectDef>")]
     source: Option<Rect>,
+    #[serde_as(as = "Vec2Def")]
     offset: Vec2,
     centered: bool,
     flip_h: bool,
     layer: i32,
+    /// Multiplied with the texture when drawing.
+    #[serde_as(as = "ColorDef")]
+    #[serde(default = "default_tint")]
+    tint: Color,
+    /// Added to the entity's y when depth sorting, so a tall sprite can sort by its feet.
+    #[serde(default)]
+    sort_offset: f32,
+    /// Multiplied into the tint's alpha. Kept apart so fading doesn't lose the tint's own.
+    #[serde(default = "default_alpha")]
+    alpha: f32,
+}
+
+fn default_tint() -> Color {
+    WHITE
+}
+
+fn default_alpha() -> f32 {
+    1.0
+}
+
+impl Default for SpriteComponent {
+    fn default() -> Self {
+        Self {
+            texture: Default::default(),
+            source: None,
+            offset: Default::default(),
+            centered: false,
+            flip_h: false,
+            layer: 0,
+            tint: default_tint(),
+            sort_offset: 0.0,
+            alpha: default_alpha(),
+        }
+    }
 }
 
 impl SpriteComponent {
+    fn color(&self) -> Color {
+        Color {
+            a: self.tint.a * self.alpha,
+            ..self.tint
+        }
+    }
+
+    fn sort_key(&self, pos: Vec2) -> f32 {
+        pos.y + self.sort_offset
+    }
+
     fn size(&self, assets: &Assets) -> Vec2 {
         self.source.as_ref().map(Rect::size).unwrap_or_else(|| {
             let tex = assets.get(&self.texture);
@@ -101,6 +309,21 @@ impl SpriteComponent {
             }
     }
 
+    /// Whether the pixel drawn at `local` (relative to the top-left of `bounds`) is visible.
+    fn is_opaque_at(&self, assets: &Assets, local: Vec2) -> bool {
+        let size = self.size(assets);
+        let x = if self.flip_h {
+            size.x - 1.0 - local.x.floor()
+        } else {
+            local.x
+        };
+        let source = self.source.map(|source| source.point()).unwrap_or_default();
+        matches!(
+            assets.sample_alpha(&self.texture, source + vec2(x, local.y)),
+            Some(alpha) if alpha > 0.0
+        )
+    }
+
     fn bounds(&self, assets: &Assets) -> Rect {
         // self.source
         //     .unwrap_or(Rect {
@@ -123,24 +346,97 @@ impl SpriteComponent {
     }
 }
 
+#[serde_as]
 #[derive(Clone, Copy, Serialize, Deserialize)]
 struct AnimationComponent {
     id: AnimatedSpriteId,
     animation: Ustr,
     frame: usize,
+    #[serde_as(as = "Vec2Def")]
     offset: Vec2,
+    /// Whether to push an `Event::AnimationLooped` each time the animation wraps around.
+    #[serde(default)]
+    notify_loops: bool,
+    /// Holds the current frame, still applying its offset and source. Useful for dormant
+    /// creatures and stopped clocks as well as posing sprites in the editor.
+    #[serde(default)]
+    paused: bool,
+    /// Frames advanced per tick; fractional speeds build up in `progress`.
+    #[serde(default = "default_animation_speed")]
+    speed: f32,
+    #[serde(skip)]
+    progress: f32,
+}
+
+fn default_animation_speed() -> f32 {
+    1.0
+}
+
+impl Default for AnimationComponent {
+    fn default() -> Self {
+        Self {
+            id: Default::default(),
+            animation: ustr("Idle"),
+            frame: 0,
+            offset: Default::default(),
+            notify_loops: false,
+            paused: false,
+            speed: default_animation_speed(),
+            progress: 0.0,
+        }
+    }
 }
 
+impl AnimationComponent {
+    /// Switches to `animation`, starting it from the beginning if it wasn't already playing.
+    fn set_animation(&mut self, animation: Ustr) {
+        if self.animation != animation {
+            self.animation = animation;
+            self.frame = 0;
+            self.progress = 0.0;
+        }
+    }
+}
+
+#[serde_as]
 #[derive(Clone, Copy, Serialize, Deserialize)]
 struct CollisionComponent {
-    #[serde(with = "RectDef")]
+    #[serde_as(as = "NormalizedRectDef")]
     bounds: Rect,
 }
 
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize)]
 enum InteractableType {
     Lamp,
     Ghost,
+    Pickup,
+    /// Shows its text and nothing else.
+    Sign(String),
+    /// Gives the player `item` the first time it's opened.
+    Chest {
+        item: Ustr,
+        opened: bool,
+    },
+    /// Loads another map and puts the player at `target_pos` in it.
+    Door {
+        target_map: String,
+        #[serde_as(as = "Vec2Def")]
+        target_pos: Vec2,
+    },
+}
+
+impl InteractableType {
+    fn name(&self) -> &'static str {
+        match self {
+            InteractableType::Lamp => "Lamp",
+            InteractableType::Ghost => "Ghost",
+            InteractableType::Pickup => "Pickup",
+            InteractableType::Sign(_) => "Sign",
+            InteractableType::Chest { .. } => "Chest",
+            InteractableType::Door { .. } => "Door",
+        }
+    }
 }
 
 impl Default for InteractableType {
@@ -149,12 +445,89 @@ impl Default for InteractableType {
     }
 }
 
-#[derive(Clone, Copy, Serialize, Deserialize, Default)]
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize, Default)]
 struct Interactable {
-    #[serde(with = "RectDef")]
+    #[serde_as(as = "NormalizedRectDef")]
     bounds: Rect,
     interaction: InteractableType,
     priority: i32,
+    /// How far outside `bounds` an entity can stand and still interact.
+    #[serde(default)]
+    range: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+struct Inventory {
+    items: UstrMap<u32>,
+}
+
+impl Inventory {
+    fn add(&mut self, item: Ustr, count: u32) {
+        *self.items.entry(item).or_insert(0) += count;
+    }
+
+    fn count(&self, item: Ustr) -> u32 {
+        self.items.get(&item).cloned().unwrap_or(0)
+    }
+}
+
+/// An item lying in the world, collected into the player's inventory when interacted with.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct ItemPickup {
+    item: Ustr,
+    count: u32,
+}
+
+impl Default for ItemPickup {
+    fn default() -> Self {
+        Self {
+            item: ustr("item"),
+            count: 1,
+        }
+    }
+}
+
+/// Lets scripts look an entity up by name rather than holding onto its id.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct Name(Ustr);
+
+/// Collects an `ItemPickup` as soon as the player walks within `radius`, no button needed.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct AutoCollectComponent {
+    radius: f32,
+}
+
+impl Default for AutoCollectComponent {
+    fn default() -> Self {
+        Self { radius: 8.0 }
+    }
+}
+
+/// How far beyond its radius an auto-collected item starts sliding towards the player.
+const MAGNET_RANGE: f32 = 6.0;
+const MAGNET_SPEED: f32 = 1.5;
+
+/// How fast the player walks, in pixels per tick in any direction. Swap it out with
+/// `Game::add_component` to sprint or slow down; without one, the player walks at the default.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct MovementComponent {
+    speed: f32,
+}
+
+impl Default for MovementComponent {
+    fn default() -> Self {
+        Self { speed: 1.0 }
+    }
+}
+
+/// Keeps an entity pinned to another, such as a hat on the player or a flame on a lamp.
+#[serde_as]
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct ParentComponent {
+    parent: Entity,
+    #[serde_as(as = "Vec2Def")]
+    local_offset: Vec2,
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
@@ -162,13 +535,184 @@ struct FollowComponent {
     target: Entity,
     max_distance: f32,
     speed: f32,
+    /// Move along one axis at a time instead of straight at the target, like the player does.
+    #[serde(default)]
+    snap_to_axis: bool,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct HealthComponent {
+    health: u32,
+    max_health: u32,
+}
+
+/// A dark blob under an entity's feet, drawn just before its sprite.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct ShadowComponent {
+    /// Width as a fraction of the sprite's.
+    size: f32,
+    alpha: f32,
+}
+
+impl Default for ShadowComponent {
+    fn default() -> Self {
+        Self {
+            size: 0.8,
+            alpha: 0.3,
+        }
+    }
+}
+
+impl ShadowComponent {
+    /// `feet` is the rect the shadow sits at the bottom of: the collision box if there is one,
+    /// otherwise the sprite.
+    fn draw(&self, assets: &Assets, sprite: &SpriteComponent, feet: Rect) {
+        let width = sprite.size(assets).x * self.size;
+        draw_ellipse(
+            vec2(feet.x + feet.w / 2.0, feet.bottom()),
+            vec2(width / 2.0, width / 6.0),
+            Color::new(0.0, 0.0, 0.0, self.alpha),
+        );
+    }
+}
+
+/// Eases a sprite's alpha towards `target` by `speed` a step, removing itself once there.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct FadeComponent {
+    target: f32,
+    speed: f32,
+}
+
+impl Default for FadeComponent {
+    fn default() -> Self {
+        Self {
+            target: 1.0,
+            speed: 0.05,
+        }
+    }
+}
+
+/// Frames a freshly hit sprite spends flashing.
+const FLASH_DURATION: u32 = 12;
+const FLASH_COLOR: Color = RED;
+
+/// Pulls a sprite's tint towards `FLASH_COLOR`, fading back over `FLASH_DURATION` frames.
+/// Only ever added at runtime, so it isn't saved with the map.
+#[derive(Clone, Copy)]
+struct FlashComponent {
+    timer: u32,
+}
+
+impl FlashComponent {
+    fn apply(&self, tint: Color) -> Color {
+        let t = self.timer as f32 / FLASH_DURATION as f32;
+        Color::new(
+            tint.r + (FLASH_COLOR.r - tint.r) * t,
+            tint.g + (FLASH_COLOR.g - tint.g) * t,
+            tint.b + (FLASH_COLOR.b - tint.b) * t,
+            tint.a,
+        )
+    }
+}
+
+/// A grid of tiles cut from a single tileset texture, drawn with its top-left corner at the
+/// entity's position.
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize, Default)]
+struct TilemapComponent {
+    tileset: TextureId,
+    #[serde_as(as = "Vec2Def")]
+    tile_size: Vec2,
+    width: usize,
+    tiles: Vec<Option<u32>>,
+    layer: i32,
+}
+
+impl TilemapComponent {
+    fn tile_source(&self, assets: &Assets, tile: u32) -> Rect {
+        let columns = ((assets.get(&self.tileset).width() / self.tile_size.x) as u32).max(1);
+        Rect::new(
+            (tile % columns) as f32 * self.tile_size.x,
+            (tile / columns) as f32 * self.tile_size.y,
+            self.tile_size.x,
+            self.tile_size.y,
+        )
+    }
+
+    /// Index into `tiles` of the cell under `point`, for a tilemap drawn at `pos`.
+    fn cell_at(&self, pos: Vec2, point: Vec2) -> Option<usize> {
+        let local = (point - pos) / self.tile_size;
+        if local.x < 0.0 || local.y < 0.0 || local.x as usize >= self.width {
+            return None;
+        }
+        let index = local.y as usize * self.width + local.x as usize;
+        (index < self.tiles.len()).then_some(index)
+    }
+
+    /// The cells above, right of, below and left of `cell`, where they exist.
+    fn neighbours(&self, cell: usize) -> [Option<usize>; 4] {
+        let x = cell % self.width;
+        let in_bounds = |cell: usize| (cell < self.tiles.len()).then_some(cell);
+        [
+            cell.checked_sub(self.width),
+            (x + 1 < self.width).then(|| cell + 1).and_then(in_bounds),
+            in_bounds(cell + self.width),
+            (x > 0).then(|| cell - 1),
+        ]
+    }
+
+    /// Re-picks the tiles of `cell` and its neighbours from `set` wherever they're already
+    /// from it, so walls join up with the walls around them. See `TilesetData::autotile`.
+    fn autotile(&mut self, cell: usize, set: &[u32; 16]) {
+        let in_set = |tiles: &[Option<u32>], cell: usize| matches!(tiles[cell], Some(tile) if set.contains(&tile));
+        let cells = std::iter::once(Some(cell)).chain(self.neighbours(cell));
+        for cell in cells.flatten().collect::<Vec<_>>() {
+            if !in_set(&self.tiles, cell) {
+                continue;
+            }
+            let mut mask = 0;
+            for (bit, neighbour) in self.neighbours(cell).iter().enumerate() {
+                if matches!(neighbour, Some(neighbour) if in_set(&self.tiles, *neighbour)) {
+                    mask |= 1 << bit;
+                }
+            }
+            self.tiles[cell] = Some(set[mask]);
+        }
+    }
+
+    fn size(&self) -> Vec2 {
+        let rows = (self.tiles.len() + self.width - 1) / self.width.max(1);
+        vec2(self.width as f32, rows as f32) * self.tile_size
+    }
+
+    /// Draws the tiles as they are `ticks` steps in, for tiles the tileset animates.
+    fn draw(&self, assets: &Assets, pos: Vec2, ticks: u64) {
+        let texture = *assets.get(&self.tileset);
+        for (i, tile) in self.tiles.iter().enumerate() {
+            if let Some(tile) = *tile {
+                let tile = assets.animated_tile(self.tileset, tile, ticks);
+                let x = (i % self.width) as f32 * self.tile_size.x;
+                let y = (i / self.width) as f32 * self.tile_size.y;
+                draw_texture_ex(
+                    texture,
+                    pos.x + x,
+                    pos.y + y,
+                    WHITE,
+                    DrawTextureParams {
+                        source: Some(self.tile_source(assets, tile)),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+    }
 }
 
-#[derive(Deserialize)]
 pub struct Overworld {
-    #[serde(deserialize_with = "deserialize_world")]
     world: World,
     player: Entity,
+    /// Steps simulated since the map was loaded, shared so animated tiles all stay in step.
+    ticks: u64,
 }
 
 impl Overworld {
@@ -183,6 +727,9 @@ impl Overworld {
                 flip_h: false,
                 layer: -1,
                 centered: false,
+                tint: WHITE,
+                sort_offset: 0.0,
+                alpha: 1.0,
             },
             CollisionComponent {
                 bounds: Rect {
@@ -202,6 +749,9 @@ impl Overworld {
                 flip_h: false,
                 layer: -1,
                 centered: false,
+                tint: WHITE,
+                sort_offset: 0.0,
+                alpha: 1.0,
             },
             CollisionComponent {
                 bounds: Rect {
@@ -221,23 +771,33 @@ impl Overworld {
                 flip_h: false,
                 layer: -1,
                 centered: true,
+                tint: WHITE,
+                sort_offset: 0.0,
+                alpha: 1.0,
             },
         ));
         let player = world.spawn((
             Position(vec2(320., 180.)),
             SpriteComponent {
-                texture: TextureId::AnimatedSpriteId(assets.char_sprite),
+                texture: TextureId::AnimatedSpriteId(assets.get_sprite("maribelle")),
                 source: None,
                 offset: Default::default(),
                 flip_h: false,
                 layer: 0,
                 centered: false,
+                tint: WHITE,
+                sort_offset: 0.0,
+                alpha: 1.0,
             },
             AnimationComponent {
-                id: assets.char_sprite,
-                animation: Ustr::from("Idle").unwrap(),
+                id: assets.get_sprite("maribelle"),
+                animation: ustr("Idle"),
                 frame: 0,
                 offset: Default::default(),
+                notify_loops: false,
+                paused: false,
+                speed: 1.0,
+                progress: 0.0,
             },
             CollisionComponent {
                 bounds: Rect {
@@ -248,94 +808,264 @@ impl Overworld {
                 },
             },
         ));
-        Self { world, player }
+        Self {
+            world,
+            player,
+            ticks: 0,
+        }
     }
 
     fn follow(&mut self) {
-        let mut adjustments = Vec::new();
+        // Read every target up front so the followers can be moved in one mutable pass. Chained
+        // followers see where their target was at the start of the frame.
+        let targets: HashMap<Entity, Vec2> = self
+            .world
+            .query::<&FollowComponent>()
+            .iter()
+            .filter_map(|(_, follow)| {
+                let target_pos = self.world.get::<Position>(follow.target).ok()?.0;
+                Some((follow.target, target_pos))
+            })
+            .collect();
+        let mut moved = Vec::new();
         for (id, (Position(pos), follow)) in
-            self.world.query::<(&Position, &FollowComponent)>().iter()
+            self.world.query_mut::<(&mut Position, &FollowComponent)>()
         {
-            if let Ok(mut query) = self.world.query_one::<&Position>(follow.target) {
-                if let Some(Position(target_pos)) = query.get() {
-                    let x_diff = target_pos.x - pos.x;
-                    let y_diff = target_pos.y - pos.y;
-                    if x_diff.abs() + y_diff.abs() > follow.max_distance {
-                        let adjustment = if x_diff.abs() > y_diff.abs() {
-                            vec2(x_diff.abs().min(follow.speed).copysign(x_diff), 0.)
-                        } else {
-                            vec2(0., y_diff.abs().min(follow.speed).copysign(y_diff))
-                        };
-
-                        adjustments.push((id, adjustment));
-                    }
+            if let Some(target_pos) = targets.get(&follow.target) {
+                let diff = *target_pos - *pos;
+                let distance = diff.length();
+                if distance > follow.max_distance {
+                    *pos += if !follow.snap_to_axis {
+                        diff / distance * distance.min(follow.speed)
+                    } else if diff.x.abs() > diff.y.abs() {
+                        vec2(diff.x.abs().min(follow.speed).copysign(diff.x), 0.)
+                    } else {
+                        vec2(0., diff.y.abs().min(follow.speed).copysign(diff.y))
+                    };
+                    moved.push(id);
                 }
             }
         }
-        for (id, adjustment) in adjustments {
-            let query = self.world.query_one_mut::<&mut Position>(id).unwrap();
-            query.0 += adjustment;
+        // Sliding diagonally along a wall pushes out the same way the player does
+        for id in moved {
+            self.resolve_penetrations(id);
         }
     }
 
-    fn resolve_penetrations(&mut self, entity: Entity) {
-        if let Ok((&Position(pos), &CollisionComponent { bounds })) = self
-            .world
-            .query_one_mut::<(&Position, &CollisionComponent)>(entity)
-        {
-            let mut our_box = bounds.offset(pos);
+    /// Whether pointing `follower` at `target` would have it chasing itself, directly or round
+    /// a loop of other followers.
+    fn follow_loops(&self, follower: Entity, target: Entity) -> bool {
+        let mut seen = HashSet::new();
+        let mut current = target;
+        while current != follower {
+            // A loop that doesn't pass through `follower` is someone else's problem
+            if !seen.insert(current) {
+                return false;
+            }
+            match self.world.get::<FollowComponent>(current) {
+                Ok(follow) => current = follow.target,
+                Err(_) => return false,
+            }
+        }
+        true
+    }
 
-            for (
-                id,
-                (
-                    Position(other_pos),
-                    CollisionComponent {
-                        bounds: other_bounds,
-                    },
-                ),
-            ) in self.world.query_mut::<(&Position, &CollisionComponent)>()
+    /// Stops every follower that's chasing itself, so a bad save can't leave entities
+    /// vibrating in place. Each loop is broken at the first follower found in it.
+    fn break_follow_loops(&mut self) {
+        let followers: Vec<(Entity, Entity)> = self
+            .world
+            .query::<&FollowComponent>()
+            .iter()
+            .map(|(entity, follow)| (entity, follow.target))
+            .collect();
+        for (follower, target) in followers {
+            if self.world.get::<FollowComponent>(follower).is_ok()
+                && self.follow_loops(follower, target)
             {
-                if id == entity {
-                    continue;
+                println!(
+                    "Entity {} ends up following itself; removing its FollowComponent",
+                    follower.id()
+                );
+                self.world.remove_one::<FollowComponent>(follower).ok();
+            }
+        }
+    }
+
+    /// Where an attached entity should be, found by walking up its chain of parents.
+    /// Returns `None` if an ancestor is missing or the chain loops.
+    fn attached_position(&self, entity: Entity) -> Option<Vec2> {
+        const MAX_DEPTH: usize = 16;
+        let mut offset = Vec2::ZERO;
+        let mut current = entity;
+        for _ in 0..MAX_DEPTH {
+            match self.world.get::<ParentComponent>(current) {
+                Ok(parent) => {
+                    offset += parent.local_offset;
+                    current = parent.parent;
                 }
-                let other_box = other_bounds.offset(*other_pos);
-                if our_box.overlaps(&other_box) {
-                    let leftwards_motion = other_box.left() - our_box.right();
-                    let rightwards_motion = other_box.right() - our_box.left();
-                    let upwards_motion = other_box.top() - our_box.bottom();
-                    let downwards_motion = other_box.bottom() - our_box.top();
-                    let abs_cmp = |x: &f32, y: &f32| x.abs().partial_cmp(&y.abs()).unwrap();
-                    let min_horiz = std::cmp::min_by(leftwards_motion, rightwards_motion, abs_cmp);
-                    let min_vert = std::cmp::min_by(upwards_motion, downwards_motion, abs_cmp);
-
-                    match min_horiz.abs().partial_cmp(&min_vert.abs()).unwrap() {
-                        std::cmp::Ordering::Less | std::cmp::Ordering::Equal => {
-                            our_box.x += min_horiz;
-                        }
-                        std::cmp::Ordering::Greater => {
-                            our_box.y += min_vert;
-                        }
-                    }
+                Err(_) => {
+                    return self
+                        .world
+                        .get::<Position>(current)
+                        .ok()
+                        .map(|p| p.0 + offset)
                 }
             }
+        }
+        None
+    }
 
-            self.world.query_one_mut::<&mut Position>(entity).unwrap().0 +=
-                our_box.point() - bounds.point() - pos;
+    fn update_attachments(&mut self) {
+        let mut moves = Vec::new();
+        let mut orphans = Vec::new();
+        for (id, parent) in self.world.query::<&ParentComponent>().iter() {
+            if !self.world.contains(parent.parent) {
+                orphans.push(id);
+            } else if let Some(pos) = self.attached_position(id) {
+                moves.push((id, pos));
+            }
+        }
+        for (id, pos) in moves {
+            if let Ok(Position(child_pos)) = self.world.query_one_mut::<&mut Position>(id) {
+                *child_pos = pos;
+            }
+        }
+        // Decorations don't outlive what they're attached to
+        for id in orphans {
+            self.world.despawn(id).unwrap();
         }
     }
 
-    fn draw(&self, assets: &Assets) {
-        let mut query = self.world.query::<(&Position, &SpriteComponent)>();
-        let mut drawables: Vec<_> = query.iter().collect();
+    fn resolve_penetrations(&mut self, entity: Entity) {
+        const MAX_ITERATIONS: usize = 8;
+
+        if let Ok((&Position(pos), &CollisionComponent { bounds })) = self
+            .world
+            .query_one_mut::<(&Position, &CollisionComponent)>(entity)
+        {
+            let mut our_box = bounds.offset(pos);
+            let other_boxes: Vec<Rect> = self
+                .world
+                .query_mut::<(&Position, &CollisionComponent)>()
+                .into_iter()
+                .filter(|(id, _)| *id != entity)
+                .map(
+                    |(_, (Position(other_pos), CollisionComponent { bounds }))| {
+                        bounds.offset(*other_pos)
+                    },
+                )
+                .collect();
+
+            // Push out of the deepest overlap first and repeat, so being wedged between several
+            // boxes resolves the same way regardless of the order they're stored in
+            for _ in 0..MAX_ITERATIONS {
+                let deepest = other_boxes
+                    .iter()
+                    .filter_map(|other_box| {
+                        types::Rect::from(our_box)
+                            .intersection(&(*other_box).into())
+                            .map(|overlap| (other_box, overlap.w * overlap.h))
+                    })
+                    .filter(|(_, area)| *area > 0.0)
+                    .max_by(|(_, area1), (_, area2)| area1.partial_cmp(area2).unwrap());
+                let other_box = match deepest {
+                    Some((other_box, _)) => other_box,
+                    None => break,
+                };
+
+                let leftwards_motion = other_box.left() - our_box.right();
+                let rightwards_motion = other_box.right() - our_box.left();
+                let upwards_motion = other_box.top() - our_box.bottom();
+                let downwards_motion = other_box.bottom() - our_box.top();
+                let abs_cmp = |x: &f32, y: &f32| x.abs().partial_cmp(&y.abs()).unwrap();
+                let min_horiz = std::cmp::min_by(leftwards_motion, rightwards_motion, abs_cmp);
+                let min_vert = std::cmp::min_by(upwards_motion, downwards_motion, abs_cmp);
+
+                match min_horiz.abs().partial_cmp(&min_vert.abs()).unwrap() {
+                    std::cmp::Ordering::Less | std::cmp::Ordering::Equal => {
+                        our_box.x += min_horiz;
+                    }
+                    std::cmp::Ordering::Greater => {
+                        our_box.y += min_vert;
+                    }
+                }
+            }
+
+            self.world.query_one_mut::<&mut Position>(entity).unwrap().0 +=
+                our_box.point() - bounds.point() - pos;
+        }
+    }
+
+    /// The area covered by every sprite and tilemap, or `None` if there's nothing to draw.
+    fn bounds(&self, assets: &Assets) -> Option<Rect> {
+        let sprites = self
+            .world
+            .query::<(&Position, &SpriteComponent)>()
+            .iter()
+            .map(|(_, (Position(pos), sprite))| sprite.bounds(assets).offset(*pos))
+            .collect::<Vec<_>>();
+        let tilemaps = self
+            .world
+            .query::<(&Position, &TilemapComponent)>()
+            .iter()
+            .map(|(_, (Position(pos), tilemap))| {
+                Rect::new(pos.x, pos.y, tilemap.size().x, tilemap.size().y)
+            })
+            .collect::<Vec<_>>();
+        sprites
+            .into_iter()
+            .chain(tilemaps)
+            .reduce(|a, b| a.combine_with(b))
+    }
+
+    fn draw(&self, assets: &Assets, hidden_layers: &HashSet<i32>) {
+        let mut tilemap_query = self.world.query::<(&Position, &TilemapComponent)>();
+        let mut tilemaps: Vec<_> = tilemap_query
+            .iter()
+            .filter(|(_, (_, tilemap))| !hidden_layers.contains(&tilemap.layer))
+            .collect();
+        tilemaps.sort_by_key(|(_, (_, tilemap))| tilemap.layer);
+        let mut tilemaps = tilemaps.into_iter().peekable();
+
+        let mut query = self.world.query::<(
+            &Position,
+            &SpriteComponent,
+            Option<&FlashComponent>,
+            Option<&ShadowComponent>,
+            Option<&CollisionComponent>,
+        )>();
+        let mut drawables: Vec<_> = query
+            .iter()
+            .filter(|(_, (_, sprite, ..))| !hidden_layers.contains(&sprite.layer))
+            .collect();
         drawables.sort_by(
-            |(_, (Position(pos1), sprite1)), (_, (Position(pos2), sprite2))| {
-                sprite1
-                    .layer
-                    .cmp(&sprite2.layer)
-                    .then(pos1.y.partial_cmp(&pos2.y).unwrap())
+            |(_, (Position(pos1), sprite1, ..)), (_, (Position(pos2), sprite2, ..))| {
+                sprite1.layer.cmp(&sprite2.layer).then(
+                    sprite1
+                        .sort_key(*pos1)
+                        .partial_cmp(&sprite2.sort_key(*pos2))
+                        .unwrap(),
+                )
             },
         );
-        for (_id, (&Position(pos), sprite)) in drawables {
+        // macroquad already merges consecutive quads from the same texture into one draw call,
+        // so sprites only cost a new call where the sort switches texture. Grouping them by
+        // texture instead would change which sprite ends up on top.
+        for (_id, (&Position(pos), sprite, flash, shadow, collision)) in drawables {
+            // Tilemaps sit underneath any sprites sharing their layer
+            while let Some((_, (Position(tilemap_pos), tilemap))) =
+                tilemaps.next_if(|(_, (_, tilemap))| tilemap.layer <= sprite.layer)
+            {
+                tilemap.draw(assets, *tilemap_pos, self.ticks);
+            }
+            if let Some(shadow) = shadow {
+                let feet = match collision {
+                    Some(collision) => collision.bounds,
+                    None => sprite.bounds(assets),
+                };
+                shadow.draw(assets, sprite, feet.offset(pos));
+            }
             let offset = sprite.offset(assets);
             let true_x = pos.x + offset.x;
             let true_y = pos.y + offset.y;
@@ -343,7 +1073,7 @@ impl Overworld {
                 *assets.get(&sprite.texture),
                 true_x,
                 true_y,
-                WHITE,
+                flash.map_or(sprite.color(), |flash| flash.apply(sprite.color())),
                 DrawTextureParams {
                     source: sprite.source,
                     flip_x: sprite.flip_h,
@@ -351,17 +1081,36 @@ impl Overworld {
                 },
             );
         }
+        for (_id, (Position(pos), tilemap)) in tilemaps {
+            tilemap.draw(assets, *pos, self.ticks);
+        }
     }
 
-    fn tick_animations(&mut self, assets: &Assets) {
-        for (_id, animation) in self.world.query_mut::<&mut AnimationComponent>() {
-            animation.frame += 1;
-            if animation.frame
-                >= assets
-                    .get(&assets.char_sprite)
-                    .get_anim_length(animation.animation.as_str())
-            {
-                animation.frame = 0;
+    fn tick_animations(&mut self, assets: &Assets, events: &mut Vec<Event>) {
+        for (id, animation) in self.world.query_mut::<&mut AnimationComponent>() {
+            if animation.paused {
+                continue;
+            }
+            let length = assets
+                .get(&animation.id)
+                .get_anim_length(animation.animation.as_str());
+            // An unknown animation has no frames to loop through
+            if length == 0 {
+                continue;
+            }
+            animation.progress += animation.speed;
+            while animation.progress >= 1.0 {
+                animation.progress -= 1.0;
+                animation.frame += 1;
+                if animation.frame >= length {
+                    animation.frame = 0;
+                    if animation.notify_loops {
+                        events.push(Event::AnimationLooped {
+                            entity: id,
+                            animation: animation.animation,
+                        });
+                    }
+                }
             }
         }
 
@@ -382,138 +1131,433 @@ impl Overworld {
         }
     }
 
-    fn update(&mut self, assets: &Assets, events: &mut Vec<Event>, allow_input: bool) {
+    fn update(
+        &mut self,
+        assets: &Assets,
+        events: &mut Vec<Event>,
+        allow_input: bool,
+        pressed: &HashSet<KeyCode>,
+    ) {
         if allow_input {
-            if let Ok((Position(pos), sprite, animation)) = self.world.query_one_mut::<(
-                &mut Position,
-                &mut SpriteComponent,
-                &mut AnimationComponent,
-            )>(self.player)
+            if let Ok((Position(pos), sprite, animation, movement)) =
+                self.world.query_one_mut::<(
+                    &mut Position,
+                    &mut SpriteComponent,
+                    &mut AnimationComponent,
+                    Option<&MovementComponent>,
+                )>(self.player)
             {
-                if is_key_down(KeyCode::Up) {
-                    animation.animation = ustr("Back");
-                    sprite.flip_h = false;
-                    pos.y -= 1.0;
-                }
-                if is_key_down(KeyCode::Down) {
-                    animation.animation = ustr("Idle");
-                    sprite.flip_h = false;
-                    pos.y += 1.0;
+                let mut input = Vec2::ZERO;
+                for (key, dir) in [
+                    (KeyCode::Up, Direction::Up),
+                    (KeyCode::Down, Direction::Down),
+                    (KeyCode::Left, Direction::Left),
+                    (KeyCode::Right, Direction::Right),
+                ] {
+                    if is_key_down(key) {
+                        input += dir.step();
+                    }
                 }
-                if is_key_down(KeyCode::Left) {
-                    animation.animation = ustr("Right");
-                    sprite.flip_h = true;
-                    pos.x -= 1.0;
+                // Diagonals would otherwise be faster than walking straight
+                if input.length() > 1.0 {
+                    input = input.normalize();
                 }
-                if is_key_down(KeyCode::Right) {
-                    animation.animation = ustr("Right");
-                    sprite.flip_h = false;
-                    pos.x += 1.0;
+                if let Some(dir) = Direction::dominant(input) {
+                    let (name, flip_h) = assets.get(&animation.id).anim_for("", dir);
+                    animation.set_animation(name);
+                    sprite.flip_h = flip_h;
                 }
+                *pos += input * movement.copied().unwrap_or_default().speed;
             }
             self.follow();
         }
         self.resolve_penetrations(self.player);
+        self.update_attachments();
         if allow_input {
-            if is_key_pressed(KeyCode::Space) {
+            if pressed.contains(&KeyCode::Space) {
                 self.interact(self.player, events);
             }
+            self.auto_collect(events);
+        }
+        self.tick_animations(assets, events);
+        self.ticks += 1;
+        self.tick_flashes();
+        self.tick_fades();
+    }
+
+    fn tick_fades(&mut self) {
+        let mut finished = Vec::new();
+        for (id, (sprite, fade)) in self
+            .world
+            .query_mut::<(&mut SpriteComponent, &FadeComponent)>()
+        {
+            let delta = fade.target - sprite.alpha;
+            if delta.abs() <= fade.speed {
+                sprite.alpha = fade.target;
+                finished.push(id);
+            } else {
+                sprite.alpha += fade.speed * delta.signum();
+            }
+        }
+        for id in finished {
+            self.world.remove_one::<FadeComponent>(id).unwrap();
+        }
+    }
+
+    fn auto_collect(&mut self, events: &mut Vec<Event>) {
+        let player_pos = match self.world.get::<Position>(self.player) {
+            Ok(pos) => pos.0,
+            Err(_) => return,
+        };
+        let mut collected = Vec::new();
+        for (id, (Position(pos), auto_collect)) in self
+            .world
+            .query_mut::<(&mut Position, &AutoCollectComponent)>()
+        {
+            let reach = Circle {
+                center: *pos,
+                r: auto_collect.radius,
+            };
+            let magnet = Circle {
+                center: player_pos,
+                r: MAGNET_RANGE,
+            };
+            if reach.contains(player_pos) {
+                collected.push(id);
+            } else if magnet.overlaps(&reach) {
+                let to_player = player_pos - *pos;
+                *pos += to_player.normalize_or_zero() * MAGNET_SPEED.min(to_player.length());
+            }
+        }
+        for id in collected {
+            self.collect_pickup(id, events);
+        }
+    }
+
+    fn tick_flashes(&mut self) {
+        let mut finished = Vec::new();
+        for (id, flash) in self.world.query_mut::<&mut FlashComponent>() {
+            flash.timer = flash.timer.saturating_sub(1);
+            if flash.timer == 0 {
+                finished.push(id);
+            }
+        }
+        for id in finished {
+            self.world.remove_one::<FlashComponent>(id).unwrap();
         }
-        self.tick_animations(assets);
     }
 
-    fn query_cursor_pos(&self, assets: &Assets, cursor: Vec2) -> Option<(Entity, Vec2)> {
+    /// Takes `amount` off an entity's health and flashes its sprite. Returns whether the hit
+    /// was fatal; entities without health shrug it off.
+    fn damage(&mut self, entity: Entity, amount: u32) -> bool {
+        let health = match self.world.query_one_mut::<&mut HealthComponent>(entity) {
+            Ok(health) => health,
+            Err(_) => return false,
+        };
+        health.health = health.health.saturating_sub(amount);
+        let dead = health.health == 0;
+        self.world
+            .insert_one(
+                entity,
+                FlashComponent {
+                    timer: FLASH_DURATION,
+                },
+            )
+            .unwrap();
+        dead
+    }
+
+    fn query_cursor_pos(
+        &self,
+        assets: &Assets,
+        cursor: Vec2,
+        ignored_layers: &HashSet<i32>,
+        pixel_perfect: bool,
+        pick_invisibles: bool,
+    ) -> Option<(Entity, Vec2)> {
         let mut query = self.world.query::<(&Position, &SpriteComponent)>();
-        let mut drawables: Vec<_> = query.iter().collect();
+        let mut drawables: Vec<_> = query
+            .iter()
+            .filter(|(_, (_, sprite))| !ignored_layers.contains(&sprite.layer))
+            .collect();
         drawables.sort_by(
             |(_, (Position(pos1), sprite1)), (_, (Position(pos2), sprite2))| {
-                sprite1
-                    .layer
-                    .cmp(&sprite2.layer)
-                    .then(pos1.y.partial_cmp(&pos2.y).unwrap())
+                sprite1.layer.cmp(&sprite2.layer).then(
+                    sprite1
+                        .sort_key(*pos1)
+                        .partial_cmp(&sprite2.sort_key(*pos2))
+                        .unwrap(),
+                )
             },
         );
+        let mut topmost = None;
         for (id, (Position(pos), sprite)) in drawables.iter().rev() {
             let bounds = sprite.bounds(assets).offset(Vec2::new(pos.x, pos.y));
             if bounds.contains(cursor) {
-                return Some((*id, *pos - cursor));
+                if !pixel_perfect || sprite.is_opaque_at(assets, cursor - bounds.point()) {
+                    return Some((*id, *pos - cursor));
+                }
+                topmost.get_or_insert((*id, *pos - cursor));
             }
         }
+        if topmost.is_some() || !pick_invisibles {
+            return topmost;
+        }
 
+        // Without a sprite, the only visible extent an entity has is its collision or
+        // interaction box
+        let mut query = self
+            .world
+            .query::<(
+                &Position,
+                Option<&CollisionComponent>,
+                Option<&Interactable>,
+            )>()
+            .without::<SpriteComponent>();
+        for (id, (Position(pos), collision, interactable)) in query.iter() {
+            let hit = collision
+                .map(|collision| collision.bounds)
+                .into_iter()
+                .chain(interactable.map(|interactable| interactable.bounds))
+                .any(|bounds| bounds.offset(*pos).contains(cursor));
+            if hit {
+                return Some((id, *pos - cursor));
+            }
+        }
         None
     }
 
+    /// Where `entity` shows up in world space: its sprite, or failing that its collision and
+    /// interaction boxes together, the same extents `pick` goes by.
+    fn visible_bounds(&self, assets: &Assets, entity: Entity) -> Option<Rect> {
+        let entity = self.world.entity(entity).ok()?;
+        let pos = entity.get::<Position>()?.0;
+        if let Some(sprite) = entity.get::<SpriteComponent>() {
+            return Some(sprite.bounds(assets).offset(pos));
+        }
+        let collision = entity.get::<CollisionComponent>().map(|c| c.bounds);
+        let interaction = entity.get::<Interactable>().map(|i| i.bounds);
+        collision
+            .into_iter()
+            .chain(interaction)
+            .map(|bounds| bounds.offset(pos))
+            .reduce(|a, b| a.combine_with(b))
+    }
+
+    /// Finds the interactable that `entity` would trigger if it interacted right now, along
+    /// with its bounds in world space.
+    fn interaction_target(&self, entity: Entity) -> Option<(Entity, Rect, InteractableType)> {
+        let pos = self.world.get::<Position>(entity).ok()?.0;
+
+        // Among equal priorities, prefer whichever interactable's centre is closest
+        let distance = |bounds: &Rect| (bounds.point() + bounds.size() / 2.0).distance(pos);
+        self.world
+            .query::<(&Position, &Interactable)>()
+            .iter()
+            .map(|(id, (Position(interactable_pos), interactable))| {
+                (
+                    id,
+                    interactable.bounds.offset(*interactable_pos),
+                    interactable,
+                )
+            })
+            .filter(|(_, bounds, interactable)| {
+                let reach = Circle {
+                    center: pos,
+                    r: interactable.range,
+                };
+                reach.overlaps_rect(&(*bounds).into())
+            })
+            .max_by(|(_, bounds1, interactable1), (_, bounds2, interactable2)| {
+                interactable1
+                    .priority
+                    .cmp(&interactable2.priority)
+                    .then(distance(bounds2).partial_cmp(&distance(bounds1)).unwrap())
+            })
+            .map(|(id, bounds, interactable)| (id, bounds, interactable.interaction.clone()))
+    }
+
     fn interact(&mut self, entity: Entity, events: &mut Vec<Event>) {
-        let pos = match self.world.query_one_mut::<&Position>(entity) {
-            Ok(Position(pos)) => *pos,
+        if let Some((entity, _, interaction)) = self.interaction_target(entity) {
+            events.push(Event::Interaction {
+                entity,
+                interaction,
+            });
+        }
+    }
+
+    /// Outlines the sprite of the interactable the player would use, falling back to its
+    /// interaction bounds if it has no sprite.
+    fn draw_interaction_highlight(&self, assets: &Assets) {
+        if let Some((entity, bounds, _)) = self.interaction_target(self.player) {
+            let bounds = match self
+                .world
+                .query_one::<(&Position, &SpriteComponent)>(entity)
+                .ok()
+                .as_mut()
+                .and_then(|query| query.get())
+            {
+                Some((Position(pos), sprite)) => sprite.bounds(assets).offset(*pos),
+                None => bounds,
+            };
+            draw_rectangle_lines(
+                bounds.x,
+                bounds.y,
+                bounds.w,
+                bounds.h,
+                1.0,
+                Color::new(colors::LIGHT.r, colors::LIGHT.g, colors::LIGHT.b, 0.5),
+            );
+        }
+    }
+
+    fn draw_interaction_prompt(&self, assets: &Assets) {
+        if let Some((_, bounds, _)) = self.interaction_target(self.player) {
+            let font = FontId::default();
+            draw_text_bmfont(
+                assets,
+                &font,
+                "!",
+                TextStyle {
+                    x: bounds.x + bounds.w / 2.0,
+                    y: bounds.top() - assets.get(&font).bmfont.line_height() as f32,
+                    color: colors::LIGHT,
+                    justify: Justify::Center,
+                    backing: TextBacking::None,
+                },
+            );
+        }
+    }
+
+    /// Moves `entity` up to `speed` towards `target`, returning whether it has arrived.
+    /// Entities that no longer exist count as arrived.
+    fn step_towards(&mut self, entity: Entity, target: Vec2, speed: f32) -> bool {
+        match self.world.query_one_mut::<&mut Position>(entity) {
+            Ok(Position(pos)) => {
+                let to_target = target - *pos;
+                if to_target.length() <= speed {
+                    *pos = target;
+                    true
+                } else {
+                    *pos += to_target.normalize() * speed;
+                    false
+                }
+            }
+            Err(_) => true,
+        }
+    }
+
+    fn give_item(&mut self, item: Ustr, count: u32) {
+        let player = self.player;
+        match self.world.query_one_mut::<&mut Inventory>(player) {
+            Ok(inventory) => inventory.add(item, count),
+            Err(_) => {
+                let mut inventory = Inventory::default();
+                inventory.add(item, count);
+                self.world.insert_one(player, inventory).unwrap();
+            }
+        }
+    }
+
+    fn find_by_name(&self, name: Ustr) -> Option<Entity> {
+        self.world
+            .query::<&Name>()
+            .iter()
+            .find(|(_, Name(entity_name))| *entity_name == name)
+            .map(|(id, _)| id)
+    }
+
+    fn item_count(&self, item: Ustr) -> u32 {
+        self.world
+            .get::<Inventory>(self.player)
+            .map(|inventory| inventory.count(item))
+            .unwrap_or(0)
+    }
+
+    fn collect_pickup(&mut self, entity: Entity, events: &mut Vec<Event>) {
+        let pickup = match self.world.get::<ItemPickup>(entity) {
+            Ok(pickup) => *pickup,
             Err(_) => return,
         };
+        self.give_item(pickup.item, pickup.count);
+        self.world.despawn(entity).unwrap();
+        events.push(Event::PickupCollected { item: pickup.item });
+    }
 
-        let mut interactables: Vec<_> = self
-            .world
-            .query_mut::<(&Position, &Interactable)>()
-            .into_iter()
-            .collect();
-        interactables.sort_by_key(|(_id, (Position(..), Interactable { priority, .. }))| priority);
-        for (
-            id,
-            (
-                Position(interactable_pos),
-                Interactable {
-                    bounds,
-                    interaction,
-                    ..
-                },
-            ),
-        ) in interactables.iter().rev()
-        {
-            if bounds.offset(*interactable_pos).contains(pos) {
-                events.push(Event::Interaction {
-                    entity: *id,
-                    interaction: *interaction,
-                });
-                return;
+    /// Marks a chest as opened, returning its item if it hadn't been opened already.
+    fn open_chest(&mut self, entity: Entity) -> Option<Ustr> {
+        let interactable = self.world.query_one_mut::<&mut Interactable>(entity).ok()?;
+        match &mut interactable.interaction {
+            InteractableType::Chest {
+                item,
+                opened: opened @ false,
+            } => {
+                *opened = true;
+                Some(*item)
             }
+            _ => None,
         }
     }
 
-    fn draw_collisions(&self) {
+    fn draw_collisions(&self, style: ShapeStyle) {
         for (_id, (Position(pos), CollisionComponent { bounds })) in self
             .world
             .query::<(&Position, &CollisionComponent)>()
             .iter()
         {
-            let rect = bounds.offset(*pos);
-            draw_rectangle(
-                rect.x,
-                rect.y,
-                rect.w,
-                rect.h,
-                color_u8!(99., 155., 255., 64.),
-            );
+            style.draw(bounds.offset(*pos), color_u8!(99., 155., 255., 64.));
         }
     }
 
-    fn draw_interactions(&self) {
+    fn draw_interactions(&self, style: ShapeStyle) {
         for (_id, (Position(pos), Interactable { bounds, .. })) in
             self.world.query::<(&Position, &Interactable)>().iter()
         {
-            let rect = bounds.offset(*pos);
-            draw_rectangle(
-                rect.x,
-                rect.y,
-                rect.w,
-                rect.h,
-                color_u8!(251., 242., 54., 64.),
-            );
+            style.draw(bounds.offset(*pos), color_u8!(251., 242., 54., 64.));
         }
     }
 }
 
+#[derive(Clone, Copy)]
+enum ShapeStyle {
+    Filled,
+    /// Leaves whatever is underneath visible.
+    Outline,
+}
+
+impl ShapeStyle {
+    fn draw(self, rect: Rect, color: Color) {
+        match self {
+            ShapeStyle::Filled => draw_rectangle(rect.x, rect.y, rect.w, rect.h, color),
+            ShapeStyle::Outline => {
+                // The translucent fill colour would be too faint as a thin line
+                let color = Color { a: 1.0, ..color };
+                draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, color)
+            }
+        }
+    }
+}
+
+fn draw_ellipse(center: Vec2, radii: Vec2, color: Color) {
+    const SEGMENTS: usize = 24;
+    let point = |i: usize| {
+        let angle = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+        center + vec2(angle.cos(), angle.sin()) * radii
+    };
+    for i in 0..SEGMENTS {
+        draw_triangle(center, point(i), point(i + 1), color);
+    }
+}
+
+type DialogueCondition = Box<dyn Fn(&Info, &Overworld) -> bool>;
+
 enum WaitingFor {
     Confirm(futures::channel::oneshot::Sender<()>),
     Choice(futures::channel::oneshot::Sender<usize>),
     Auto(futures::channel::oneshot::Sender<()>),
+    /// Fires once the predicate holds. The player keeps control meanwhile, so they can go and
+    /// make it true.
+    Condition(DialogueCondition, futures::channel::oneshot::Sender<()>),
     Nothing,
 }
 
@@ -529,45 +1573,174 @@ enum PortraitOrientation {
     Right,
 }
 
-#[derive(Default)]
+struct Choice {
+    text: String,
+    /// Disabled choices are still shown, but can't be picked.
+    enabled: bool,
+}
+
+impl Choice {
+    fn new(text: impl Into<String>, enabled: bool) -> Self {
+        Self {
+            text: text.into(),
+            enabled,
+        }
+    }
+}
+
+impl From<&str> for Choice {
+    fn from(text: &str) -> Self {
+        Self::new(text, true)
+    }
+}
+
+impl From<String> for Choice {
+    fn from(text: String) -> Self {
+        Self::new(text, true)
+    }
+}
+
+/// Picks `default` for the player if they haven't chosen before time runs out.
+struct ChoiceTimer {
+    remaining: f32,
+    limit: f32,
+    default: usize,
+}
+
+const DIALOGUE_BOTTOM: f32 = 352.;
+const DIALOGUE_PADDING: f32 = 40.;
+const DIALOGUE_MIN_HEIGHT: f32 = 96.;
+const DIALOGUE_MAX_HEIGHT: f32 = 224.;
+const DIALOGUE_CHARS_PER_TICK: f32 = 2.0;
+
 struct Dialogue {
     shown: bool,
     current_text: String,
-    current_progress: usize,
+    /// How many characters (not bytes) of `current_text` have been typed out. Fractional so
+    /// speeds below one character a tick still add up.
+    current_progress: f32,
+    /// How fast the text types out.
+    chars_per_tick: f32,
     waiting_for: WaitingFor,
-    choices: Option<Vec<String>>,
+    choices: Option<Vec<Choice>>,
     current_choice: usize,
+    choice_timer: Option<ChoiceTimer>,
     portrait: Option<(SpriteComponent, PortraitOrientation)>,
+    font: FontId,
+    /// Texture for the text and choice boxes, if not the usual "ninebox".
+    ninebox: Option<TextureId>,
+}
+
+impl Default for Dialogue {
+    fn default() -> Self {
+        Self {
+            shown: false,
+            current_text: String::new(),
+            current_progress: 0.0,
+            chars_per_tick: DIALOGUE_CHARS_PER_TICK,
+            waiting_for: WaitingFor::Nothing,
+            choices: None,
+            current_choice: 0,
+            choice_timer: None,
+            portrait: None,
+            font: FontId::default(),
+            ninebox: None,
+        }
+    }
 }
 
 impl Dialogue {
     fn set_text(&mut self, text: String) {
         self.shown = true;
         self.current_text = text;
-        self.current_progress = 0;
+        self.current_progress = 0.0;
     }
 
-    fn update(&mut self) {
-        self.current_progress += 1;
-        if let Some(choices) = &self.choices {
-            if is_key_pressed(KeyCode::Up) {
-                self.current_choice = match self.current_choice {
-                    0 => choices.len() - 1,
-                    _ => self.current_choice - 1,
-                };
+    /// The part of the text typed out so far, always ending on a character boundary.
+    fn shown_text(&self) -> &str {
+        match self
+            .current_text
+            .char_indices()
+            .nth(self.current_progress as usize)
+        {
+            Some((end, _)) => &self.current_text[..end],
+            None => &self.current_text,
+        }
+    }
+
+    fn fully_shown(&self) -> bool {
+        self.current_progress as usize >= self.current_text.chars().count()
+    }
+
+    /// Whether the player has to deal with the dialogue before they can move again.
+    fn blocks_input(&self) -> bool {
+        self.shown && !matches!(self.waiting_for, WaitingFor::Condition(..))
+    }
+
+    fn choice_enabled(&self, index: usize) -> bool {
+        let choice = self.choices.as_ref().and_then(|choices| choices.get(index));
+        matches!(choice, Some(c) if c.enabled)
+    }
+
+    fn first_enabled_choice(&self) -> Option<usize> {
+        self.choices.as_ref()?.iter().position(|c| c.enabled)
+    }
+
+    /// Moves the cursor `direction` places, wrapping around and skipping disabled choices.
+    fn step_choice(&mut self, direction: isize) {
+        let len = match &self.choices {
+            Some(choices) if !choices.is_empty() => choices.len() as isize,
+            _ => return,
+        };
+        let mut choice = self.current_choice as isize;
+        for _ in 0..len {
+            choice = (choice + direction).rem_euclid(len);
+            if self.choice_enabled(choice as usize) {
+                self.current_choice = choice as usize;
+                return;
             }
-            if is_key_pressed(KeyCode::Down) {
-                self.current_choice = match self.current_choice {
-                    c if c >= choices.len() - 1 => 0,
-                    _ => self.current_choice + 1,
+        }
+    }
+
+    // The script waiting on a sender may already be gone, e.g. after Shift+I, so failed sends
+    // are ignored rather than unwrapped
+    fn update(&mut self, info: &Info, overworld: &Overworld, pressed: &HashSet<KeyCode>) {
+        self.current_progress += self.chars_per_tick;
+        if pressed.contains(&KeyCode::Up) {
+            self.step_choice(-1);
+        }
+        if pressed.contains(&KeyCode::Down) {
+            self.step_choice(1);
+        }
+
+        if let Some(timer) = &mut self.choice_timer {
+            timer.remaining -= TIMESTEP;
+            if timer.remaining <= 0.0 {
+                let default = timer.default;
+                self.choice_timer = None;
+                let default = if self.choice_enabled(default) {
+                    Some(default)
+                } else {
+                    self.first_enabled_choice()
                 };
+                if let Some(default) = default {
+                    if let WaitingFor::Choice(sender) =
+                        std::mem::replace(&mut self.waiting_for, WaitingFor::Nothing)
+                    {
+                        sender.send(default).ok();
+                        self.choices = None;
+                    }
+                }
             }
         }
 
-        if self.current_progress >= self.current_text.len() {
+        if self.fully_shown() {
             match std::mem::replace(&mut self.waiting_for, WaitingFor::Nothing) {
                 WaitingFor::Auto(sender) => {
-                    sender.send(()).unwrap();
+                    sender.send(()).ok();
+                }
+                WaitingFor::Condition(condition, sender) if condition(info, overworld) => {
+                    sender.send(()).ok();
                 }
                 other => {
                     self.waiting_for = other;
@@ -575,14 +1748,15 @@ impl Dialogue {
             };
         }
 
-        if is_key_pressed(KeyCode::Space) {
+        if pressed.contains(&KeyCode::Space) {
             match std::mem::replace(&mut self.waiting_for, WaitingFor::Nothing) {
                 WaitingFor::Confirm(sender) => {
-                    sender.send(()).unwrap();
+                    sender.send(()).ok();
                 }
-                WaitingFor::Choice(sender) => {
-                    sender.send(self.current_choice).unwrap();
+                WaitingFor::Choice(sender) if self.choice_enabled(self.current_choice) => {
+                    sender.send(self.current_choice).ok();
                     self.choices = None;
+                    self.choice_timer = None;
                 }
                 other => self.waiting_for = other,
             }
@@ -607,18 +1781,31 @@ impl Dialogue {
                     },
                 );
             }
-            let num_chars = std::cmp::min(self.current_text.len(), self.current_progress);
-            let ninebox = assets.get(&assets.get_texture("ninebox"));
-            draw_nine_box(*ninebox, 32., 224., 576., 128.);
+            let ninebox = assets.get(
+                &self
+                    .ninebox
+                    .unwrap_or_else(|| assets.get_texture("ninebox")),
+            );
+            // Sized for the whole line, not just what's been typed so far, so the box doesn't
+            // jump around mid-sentence
+            let (_, text_height) = measure_bmfont(assets, &self.font, &self.current_text);
+            let height = (text_height + 2.0 * DIALOGUE_PADDING)
+                .clamp(DIALOGUE_MIN_HEIGHT, DIALOGUE_MAX_HEIGHT);
+            let top = DIALOGUE_BOTTOM - height;
+            draw_nine_box(*ninebox, 32., top, 576., height);
             draw_text_bmfont(
                 assets,
-                &self.current_text[0..num_chars],
-                72.,
-                264.,
-                colors::LIGHT,
-                Justify::Left,
+                &self.font,
+                self.shown_text(),
+                TextStyle {
+                    x: 72.,
+                    y: top + DIALOGUE_PADDING,
+                    color: colors::LIGHT,
+                    justify: Justify::Left,
+                    backing: TextBacking::None,
+                },
             );
-            if let Some(choices) = &self.choices {
+            if let Some(choices) = self.choices.as_ref().filter(|c| !c.is_empty()) {
                 let mut x = 416.;
                 let mut y = 112.;
                 let mut width = 224.;
@@ -632,22 +1819,45 @@ impl Dialogue {
                     }
                     _ => {}
                 }
+                // Keep clear of the speaker by mirroring to the other side of the screen
+                if let Some((_, PortraitOrientation::Right)) = self.portrait {
+                    x = 640. - x - width;
+                }
                 draw_nine_box(*ninebox, x, y, width, height);
+                if let Some(timer) = &self.choice_timer {
+                    let full = width - 64.;
+                    draw_rectangle(x + 32., y + height - 24., full, 4., colors::DIM);
+                    draw_rectangle(
+                        x + 32.,
+                        y + height - 24.,
+                        full * (timer.remaining / timer.limit).max(0.0),
+                        4.,
+                        colors::LIGHT,
+                    );
+                }
                 for (i, c) in choices.iter().enumerate() {
                     let draw_text = |text: &str| {
                         draw_text_bmfont(
                             assets,
+                            &self.font,
                             text,
-                            x + width - 40.,
-                            y + 40. + 30. * (i as f32),
-                            colors::LIGHT,
-                            Justify::Right,
+                            TextStyle {
+                                x: x + width - 40.,
+                                y: y + 40. + 30. * (i as f32),
+                                color: if c.enabled {
+                                    colors::LIGHT
+                                } else {
+                                    colors::DIM
+                                },
+                                justify: Justify::Right,
+                                backing: TextBacking::None,
+                            },
                         );
                     };
                     if i == self.current_choice {
-                        draw_text(&format!("> {}", c));
+                        draw_text(&format!("> {}", c.text));
                     } else {
-                        draw_text(c);
+                        draw_text(&c.text);
                     };
                 }
             }
@@ -664,49 +1874,132 @@ impl Dialogue {
 
 enum Justify {
     Left,
+    Center,
     Right,
 }
 
-fn draw_text_bmfont(assets: &Assets, text: &str, x: f32, y: f32, color: Color, justify: Justify) {
-    let bmfont = &assets.font;
-    let texture_id = assets.get_texture("font");
-    let texture = assets.get(&texture_id);
-    let char_positions = bmfont.parse(text).unwrap();
-    let draw_char_position = |c: CharPosition, offset_x: f32| {
-        draw_texture_ex(
-            *texture,
-            x + c.screen_rect.x as f32 + offset_x,
-            y + c.screen_rect.y as f32,
-            color,
-            DrawTextureParams {
-                source: Some(Rect {
-                    x: c.page_rect.x as f32,
-                    y: c.page_rect.y as f32,
-                    w: c.page_rect.width as f32,
-                    h: c.page_rect.height as f32,
-                }),
-                ..Default::default()
-            },
-        );
-    };
+/// Glyph to draw in place of characters the font doesn't have.
+const MISSING_GLYPH: char = '?';
 
-    match justify {
-        Justify::Left => {
-            for c in char_positions {
-                draw_char_position(c, 0.0)
-            }
+/// Lays out `text`, drawing `MISSING_GLYPH` for anything the font has no glyph for rather than
+/// failing on it.
+fn layout_bmfont(bmfont: &BMFont, text: &str) -> Vec<CharPosition> {
+    if let Ok(positions) = bmfont.parse(text) {
+        return positions.collect();
+    }
+    let mut buf = [0; 4];
+    let text: String = text
+        .chars()
+        .map(|c| match c {
+            '\n' => c,
+            c if bmfont.parse(c.encode_utf8(&mut buf)).is_ok() => c,
+            _ => MISSING_GLYPH,
+        })
+        .collect();
+    bmfont.parse(&text).unwrap().collect()
+}
+
+/// The width and height `text` takes up when drawn, with every line a full line tall.
+fn measure_bmfont(assets: &Assets, font: &FontId, text: &str) -> (f32, f32) {
+    measure_text(&assets.get(font).bmfont, text)
+}
+
+fn measure_text(bmfont: &BMFont, text: &str) -> (f32, f32) {
+    let char_positions = layout_bmfont(bmfont, text);
+    let lines = text.lines().count().max(1);
+    (
+        text_width(&char_positions),
+        lines as f32 * bmfont.line_height() as f32,
+    )
+}
+
+fn text_width(char_positions: &[CharPosition]) -> f32 {
+    // Glyph positions already include kerning, and a negative pair can pull the last glyph in
+    // past an earlier one, so measure the widest extent rather than the end
+    char_positions
+        .iter()
+        .map(|c| c.screen_rect.max_x())
+        .max()
+        .unwrap_or(0) as f32
+}
+
+/// Copies of text drawn in `DARK` underneath it, to lift it off busy backgrounds.
+#[derive(Clone, Copy)]
+enum TextBacking {
+    None,
+    /// One copy, 1px down and to the right.
+    Shadow,
+    /// Copies 1px away on every side.
+    Outline,
+}
+
+impl TextBacking {
+    fn offsets(self) -> &'static [(f32, f32)] {
+        match self {
+            TextBacking::None => &[],
+            TextBacking::Shadow => &[(1.0, 1.0)],
+            TextBacking::Outline => &[
+                (-1.0, -1.0),
+                (0.0, -1.0),
+                (1.0, -1.0),
+                (-1.0, 0.0),
+                (1.0, 0.0),
+                (-1.0, 1.0),
+                (0.0, 1.0),
+                (1.0, 1.0),
+            ],
         }
-        Justify::Right => {
-            let char_positions: Vec<_> = char_positions.collect();
-            let offset_x = char_positions
-                .last()
-                .map(|c| -c.screen_rect.max_x())
-                .unwrap_or(0) as f32;
-            for c in char_positions {
-                draw_char_position(c, offset_x);
-            }
+    }
+}
+
+/// Where and how `draw_text_bmfont` puts its text. `x` is the text's left edge, centre or right
+/// edge depending on `justify`, and `y` the top of the first line.
+struct TextStyle {
+    x: f32,
+    y: f32,
+    color: Color,
+    justify: Justify,
+    backing: TextBacking,
+}
+
+fn draw_text_bmfont(assets: &Assets, font: &FontId, text: &str, style: TextStyle) {
+    let TextStyle {
+        x,
+        y,
+        color,
+        justify,
+        backing,
+    } = style;
+    let font = assets.get(font);
+    let char_positions = layout_bmfont(&font.bmfont, text);
+    let offset_x = match justify {
+        Justify::Left => 0.0,
+        Justify::Center => -text_width(&char_positions) / 2.0,
+        Justify::Right => -text_width(&char_positions),
+    };
+    let draw_pass = |dx: f32, dy: f32, color: Color| {
+        for c in &char_positions {
+            draw_texture_ex(
+                *assets.get(&font.page(c.page_index)),
+                x + c.screen_rect.x as f32 + offset_x + dx,
+                y + c.screen_rect.y as f32 + dy,
+                color,
+                DrawTextureParams {
+                    source: Some(Rect {
+                        x: c.page_rect.x as f32,
+                        y: c.page_rect.y as f32,
+                        w: c.page_rect.width as f32,
+                        h: c.page_rect.height as f32,
+                    }),
+                    ..Default::default()
+                },
+            );
         }
+    };
+    for &(dx, dy) in backing.offsets() {
+        draw_pass(dx, dy, DARK);
     }
+    draw_pass(0.0, 0.0, color);
 }
 
 fn draw_nine_box(texture: Texture2D, x: f32, y: f32, width: f32, height: f32) {
@@ -746,14 +2039,31 @@ fn draw_nine_box(texture: Texture2D, x: f32, y: f32, width: f32, height: f32) {
     }
 }
 
+/// Things that happen in the world during a frame. The built-in handling lives in
+/// `Game::update`; scripts can also wait on them with `Game::wait_for_event`.
+#[derive(Clone)]
 enum Event {
     Interaction {
         entity: Entity,
         interaction: InteractableType,
     },
+    AnimationLooped {
+        entity: Entity,
+        animation: Ustr,
+    },
+    /// An item pickup was taken out of the world and added to the player's inventory.
+    PickupCollected {
+        item: Ustr,
+    },
 }
 
-#[derive(Clone, Copy)]
+/// A script waiting for the first event that passes `filter`.
+struct EventListener {
+    filter: Box<dyn Fn(&Event) -> bool>,
+    sender: futures::channel::oneshot::Sender<Event>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
 enum PlayerClass {
     Witch,
     Princess,
@@ -776,7 +2086,7 @@ impl PlayerClass {
         }
     }
 }
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 enum GhostClass {
     Ghost,
     Goblin,
@@ -801,78 +2111,356 @@ impl GhostClass {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
 struct Info {
     player_class: Option<PlayerClass>,
     ghost_class: Option<GhostClass>,
 }
 
+/// Dialogue text starting with this shakes the screen as it's shown.
+const SHAKE_MARKUP: &str = "{shake}";
+
+const PROGRESS_PATH: &str = "assets/saves/progress.json";
+const QUICKSAVE_PATH: &str = "assets/saves/quicksave.json";
+/// Names for the save menu's slots. The font has no digits, so they're spelled out.
+const SAVE_SLOTS: [&str; 3] = ["FIRST", "SECOND", "THIRD"];
+/// How long a notice stays on screen, in seconds.
+const NOTICE_DURATION: f32 = 1.5;
+
+/// Length of one simulation step. The game runs at this rate whatever the display's refresh
+/// rate, so per-step constants like the player's 1px move mean the same thing everywhere.
+const TIMESTEP: f32 = 1.0 / 60.0;
+/// Caps the catch-up after a long hitch instead of fast-forwarding through it.
+const MAX_STEPS_PER_FRAME: u32 = 5;
+/// Keys the simulation checks for presses. A frame can pass without a step, so presses are
+/// held onto until the next one.
+const LATCHED_KEYS: [KeyCode; 3] = [KeyCode::Space, KeyCode::Up, KeyCode::Down];
+
+/// An entity being walked somewhere by a script, resolved once it arrives.
+struct ScriptedMove {
+    entity: Entity,
+    target: Vec2,
+    speed: f32,
+    arrived: futures::channel::oneshot::Sender<()>,
+}
+
+/// A short message in the corner of the screen, like a quicksave confirmation.
+struct Notice {
+    text: String,
+    remaining: f32,
+}
+
 struct _Game {
     overworld: Overworld,
-    camera: Camera2D,
+    camera: Camera,
     dialogue: Dialogue,
     info: Info,
+    scripted_moves: Vec<ScriptedMove>,
+    event_listeners: Vec<EventListener>,
+    /// Stops the player moving or interacting, independently of any open dialogue.
+    input_locked: bool,
+    /// `LATCHED_KEYS` pressed since the last step.
+    pressed_keys: HashSet<KeyCode>,
+    /// The script behind the conversation the player started most recently.
+    dialogue_task: Option<DialogueTask>,
+    notice: Option<Notice>,
+    /// Seconds spent in game, carried through saves.
+    play_time: f32,
+    /// Freezes the game, including dialogue, while a menu is open over it.
+    paused: bool,
 }
 
-#[derive(Clone)]
+struct DialogueTask {
+    handle: AbortHandle,
+    finished: Rc<Cell<bool>>,
+}
+
+impl _Game {
+    /// Whether a conversation is on screen or its script is still running, in which case
+    /// another one mustn't start and fight it over the dialogue box.
+    fn dialogue_active(&self) -> bool {
+        self.dialogue.shown || matches!(&self.dialogue_task, Some(task) if !task.finished.get())
+    }
+
+    /// Starts a conversation's script, unless one is already active.
+    fn spawn_dialogue(
+        &mut self,
+        spawner: &LocalSpawner,
+        dialogue: impl Future<Output = anyhow::Result<()>> + 'static,
+    ) {
+        if self.dialogue_active() {
+            return;
+        }
+        let (task, handle) = abortable(wrap_dialogue(dialogue));
+        let finished = Rc::new(Cell::new(false));
+        let finished_inner = finished.clone();
+        spawner
+            .spawn_local(async move {
+                task.await.ok();
+                finished_inner.set(true);
+            })
+            .unwrap();
+        self.dialogue_task = Some(DialogueTask { handle, finished });
+    }
+}
+
+#[derive(Clone)]
 pub struct Game(Rc<RefCell<_Game>>);
 
 impl Game {
     fn new(assets: &Assets) -> Self {
         Self(Rc::new(RefCell::new(_Game {
             overworld: Overworld::new(assets),
-            camera: Camera2D::from_display_rect(Rect::new(0.0, 0.0, 640.0, 360.0)),
+            camera: Camera::new(Rect::new(0.0, 0.0, 640.0, 360.0)),
             dialogue: Default::default(),
             info: Default::default(),
+            scripted_moves: Vec::new(),
+            event_listeners: Vec::new(),
+            input_locked: false,
+            pressed_keys: HashSet::new(),
+            dialogue_task: None,
+            notice: None,
+            play_time: 0.0,
+            paused: false,
         })))
     }
 
+    /// Remembers this frame's presses of `LATCHED_KEYS` for the next step.
+    fn latch_input(&self) {
+        let mut this = self.0.borrow_mut();
+        // Presses meant for the pause menu shouldn't be replayed once it closes
+        if this.paused {
+            return;
+        }
+        for key in LATCHED_KEYS {
+            if is_key_pressed(key) {
+                this.pressed_keys.insert(key);
+            }
+        }
+    }
+
+    /// Advances the game by one `TIMESTEP`.
     fn update(&self, assets: &Assets, spawner: &LocalSpawner) {
         let mut this = self.0.borrow_mut();
+        let this = &mut *this;
+        if this.paused {
+            return;
+        }
         let mut events = Vec::new();
         let dialogue = this.dialogue.shown;
-        this.overworld.update(assets, &mut events, !dialogue);
+        let allow_input = !this.dialogue.blocks_input() && !this.input_locked;
+        for scripted_move in std::mem::take(&mut this.scripted_moves) {
+            if this.overworld.step_towards(
+                scripted_move.entity,
+                scripted_move.target,
+                scripted_move.speed,
+            ) {
+                scripted_move.arrived.send(()).ok();
+            } else {
+                this.scripted_moves.push(scripted_move);
+            }
+        }
+        this.camera.tick(TIMESTEP);
+        this.play_time += TIMESTEP;
+        if let Some(notice) = &mut this.notice {
+            notice.remaining -= TIMESTEP;
+            if notice.remaining <= 0.0 {
+                this.notice = None;
+            }
+        }
+        this.overworld
+            .update(assets, &mut events, allow_input, &this.pressed_keys);
+        let player = this.overworld.player;
+        if let Ok(pos) = this
+            .overworld
+            .world
+            .get::<Position>(player)
+            .map(|pos| pos.0)
+        {
+            this.camera.follow(pos);
+        }
+        if let Some(bounds) = this.overworld.bounds(assets) {
+            this.camera.clamp(bounds);
+        }
         if dialogue {
-            this.dialogue.update();
+            this.dialogue
+                .update(&this.info, &this.overworld, &this.pressed_keys);
         }
-        for event in events {
+        this.pressed_keys.clear();
+        // Handling an event can raise more, so keep going until the queue is empty
+        let mut i = 0;
+        while i < events.len() {
+            let event = events[i].clone();
+            i += 1;
+            for listener in std::mem::take(&mut this.event_listeners) {
+                if (listener.filter)(&event) {
+                    listener.sender.send(event.clone()).ok();
+                } else if !listener.sender.is_canceled() {
+                    this.event_listeners.push(listener);
+                }
+            }
             match event {
                 Event::Interaction {
                     entity,
                     interaction,
                 } => match interaction {
-                    InteractableType::Lamp => spawner
-                        .spawn_local(wrap_dialogue(lamp_dialogue_tree(self.clone())))
-                        .unwrap(),
+                    InteractableType::Lamp => {
+                        this.spawn_dialogue(spawner, lamp_dialogue_tree(self.clone()))
+                    }
+                    InteractableType::Pickup => this.overworld.collect_pickup(entity, &mut events),
                     InteractableType::Ghost => {
                         if this.info.ghost_class.is_none() {
-                            spawner
-                                .spawn_local(wrap_dialogue(ghost_meeting(self.clone(), entity)))
-                                .unwrap()
+                            this.spawn_dialogue(spawner, ghost_meeting(self.clone()));
                         } else {
-                            spawner
-                                .spawn_local(wrap_dialogue(ghost_after(self.clone())))
-                                .unwrap();
+                            this.spawn_dialogue(spawner, ghost_after(self.clone()));
+                        }
+                    }
+                    InteractableType::Sign(text) => {
+                        this.spawn_dialogue(spawner, sign_dialogue(self.clone(), text))
+                    }
+                    // Left shut if its dialogue can't start yet
+                    InteractableType::Chest { .. } => {
+                        if !this.dialogue_active() {
+                            // Mark it straight away so mashing the button can't open it twice
+                            let item = this.overworld.open_chest(entity);
+                            this.spawn_dialogue(spawner, chest_dialogue(self.clone(), item));
                         }
                     }
+                    InteractableType::Door {
+                        target_map,
+                        target_pos,
+                    } => {
+                        let game = self.clone();
+                        spawner
+                            .spawn_local(async move {
+                                game.enter_map(&target_map, target_pos)
+                                    .await
+                                    .unwrap_or_else(|e| {
+                                        println!("Failed to load {}: {}", target_map, e)
+                                    });
+                            })
+                            .unwrap();
+                    }
                 },
+                // Nothing built in reacts to these; scripts can wait for them
+                Event::AnimationLooped { .. } | Event::PickupCollected { .. } => {}
             }
         }
     }
 
-    fn draw(&self, assets: &Assets) {
+    fn draw(&self, assets: &Assets, editor: Option<&OverworldEditor>) {
         let this = self.0.borrow();
-        set_camera(&this.camera);
-        this.overworld.draw(assets);
+        match editor {
+            Some(editor) => {
+                set_camera(editor.camera().unwrap_or(this.camera.steady()));
+                clear_background(DARK);
+                this.overworld.draw(assets, editor.hidden_layers());
+            }
+            None => {
+                set_camera(&this.camera.world());
+                clear_background(DARK);
+                this.overworld.draw(assets, &HashSet::new());
+            }
+        }
+        if !this.dialogue.shown {
+            this.overworld.draw_interaction_highlight(assets);
+            this.overworld.draw_interaction_prompt(assets);
+        }
+        set_camera(this.camera.screen());
         this.dialogue.draw(assets);
+        if let Some(notice) = &this.notice {
+            draw_text_bmfont(
+                assets,
+                &FontId::default(),
+                &notice.text,
+                TextStyle {
+                    x: 8.0,
+                    y: 8.0,
+                    color: colors::LIGHT,
+                    justify: Justify::Left,
+                    backing: TextBacking::Outline,
+                },
+            );
+        }
+    }
+
+    /// Outlines every collision and interaction rect on top of the live game.
+    fn draw_debug_overlay(&self) {
+        let this = self.0.borrow();
+        set_camera(this.camera.steady());
+        this.overworld.draw_collisions(ShapeStyle::Outline);
+        this.overworld.draw_interactions(ShapeStyle::Outline);
+        set_camera(this.camera.screen());
+        // The bitmap font has no digits, so this uses macroquad's
+        draw_text(
+            &format!("PLAY TIME {}", format_play_time(self.play_time())),
+            8.0,
+            352.0,
+            16.0,
+            colors::LIGHT,
+        );
+    }
+
+    fn play_time(&self) -> Duration {
+        Duration::from_secs_f32(self.0.borrow().play_time)
+    }
+
+    /// Draws a menu on its own, like the title screen, through the game's camera so it's
+    /// letterboxed the same way.
+    fn draw_menu_screen<T: Copy>(&self, assets: &Assets, menu: &Menu<T>) {
+        set_camera(self.0.borrow().camera.screen());
+        clear_background(DARK);
+        menu.draw(assets);
+    }
+
+    /// Draws `menu` over the frozen game.
+    fn draw_menu_overlay<T: Copy>(&self, assets: &Assets, menu: &Menu<T>) {
+        set_camera(self.0.borrow().camera.screen());
+        draw_rectangle(0., 0., 640., 360., Color { a: 0.75, ..DARK });
+        menu.draw(assets);
+    }
+
+    fn set_paused(&self, paused: bool) {
+        self.0.borrow_mut().paused = paused;
+    }
+
+    /// Forgets the story so far, for starting a new game. The map is left for the caller to
+    /// reload.
+    fn reset_progress(&self) {
+        self.cancel_dialogue();
+        let mut this = self.0.borrow_mut();
+        this.info = Info::default();
+        this.play_time = 0.0;
+        this.scripted_moves.clear();
+    }
+
+    /// Puts this frame on the screen, letterboxed to the game's aspect ratio.
+    fn present(&self) {
+        self.0.borrow().camera.present();
+    }
+
+    fn shake(&self, intensity: f32, duration: f32) {
+        self.0.borrow_mut().camera.shake(intensity, duration);
+    }
+
+    /// Strips effect markup from dialogue text, starting the effects it asks for.
+    fn apply_markup(&self, text: String) -> String {
+        match text.strip_prefix(SHAKE_MARKUP) {
+            Some(rest) => {
+                self.shake(4.0, 0.3);
+                rest.to_owned()
+            }
+            None => text,
+        }
     }
 
     fn show_text<S>(&self, text: S) -> futures::channel::oneshot::Receiver<()>
     where
         S: Into<String>,
     {
+        let text = self.apply_markup(text.into());
         let mut this = self.0.borrow_mut();
-        this.dialogue.set_text(text.into());
+        this.dialogue.set_text(text);
         let (s, r) = futures::channel::oneshot::channel();
         this.dialogue.waiting_for = WaitingFor::Confirm(s);
         r
@@ -882,26 +2470,114 @@ impl Game {
     where
         S: Into<String>,
     {
+        let text = self.apply_markup(text.into());
         let mut this = self.0.borrow_mut();
-        this.dialogue.set_text(text.into());
+        this.dialogue.set_text(text);
         let (s, r) = futures::channel::oneshot::channel();
         this.dialogue.waiting_for = WaitingFor::Auto(s);
         r
     }
 
+    /// Shows `text` until `condition` holds, leaving the player free to move around meanwhile.
+    fn show_text_until<S, F>(
+        &self,
+        text: S,
+        condition: F,
+    ) -> futures::channel::oneshot::Receiver<()>
+    where
+        S: Into<String>,
+        F: Fn(&Info, &Overworld) -> bool + 'static,
+    {
+        let text = self.apply_markup(text.into());
+        let mut this = self.0.borrow_mut();
+        this.dialogue.set_text(text);
+        let (s, r) = futures::channel::oneshot::channel();
+        this.dialogue.waiting_for = WaitingFor::Condition(Box::new(condition), s);
+        r
+    }
+
+    /// Offers `choices` and resolves to the index of the one picked. With nothing to pick from,
+    /// whether the list is empty or every choice is disabled, the receiver is cancelled straight
+    /// away instead of waiting forever.
     fn show_choice(
         &self,
-        choices: impl IntoIterator<Item = impl Into<String>>,
+        choices: impl IntoIterator<Item = impl Into<Choice>>,
     ) -> futures::channel::oneshot::Receiver<usize> {
         let mut this = self.0.borrow_mut();
-        this.dialogue.choices = Some(choices.into_iter().map(Into::into).collect());
-        this.dialogue.current_choice = 0;
+        let choices: Vec<Choice> = choices.into_iter().map(Into::into).collect();
         let (s, r) = futures::channel::oneshot::channel();
+        let first_enabled = choices.iter().position(|c| c.enabled);
+        debug_assert!(
+            first_enabled.is_some(),
+            "show_choice called with no choice that can be picked"
+        );
+        let first_enabled = match first_enabled {
+            Some(index) => index,
+            None => return r,
+        };
+        this.dialogue.current_choice = first_enabled;
+        this.dialogue.choices = Some(choices);
+        this.dialogue.choice_timer = None;
         this.dialogue.waiting_for = WaitingFor::Choice(s);
         r
     }
 
+    /// Like `show_choice`, but picks `default` once `seconds` have passed without an answer. A
+    /// disabled `default` falls back to the first choice that's enabled.
+    fn show_choice_timed(
+        &self,
+        choices: impl IntoIterator<Item = impl Into<Choice>>,
+        seconds: f32,
+        default: usize,
+    ) -> futures::channel::oneshot::Receiver<usize> {
+        let r = self.show_choice(choices);
+        let mut this = self.0.borrow_mut();
+        if this.dialogue.choices.is_none() {
+            return r;
+        }
+        let default = if this.dialogue.choice_enabled(default) {
+            default
+        } else {
+            this.dialogue.current_choice
+        };
+        this.dialogue.choice_timer = Some(ChoiceTimer {
+            remaining: seconds,
+            limit: seconds,
+            default,
+        });
+        r
+    }
+
+    /// Offers the label of each branch as a choice, then runs the script of the one picked and
+    /// returns what it returns.
+    async fn menu<T>(&self, branches: Vec<Branch<'_, T>>) -> anyhow::Result<T> {
+        let choice = self.show_choice(branches.iter().map(|(label, _)| *label));
+        Self::take_branch(branches, choice.await?).await
+    }
+
+    /// Like `menu`, but takes the `default` branch once `seconds` have passed without an answer.
+    async fn menu_timed<T>(
+        &self,
+        branches: Vec<Branch<'_, T>>,
+        seconds: f32,
+        default: usize,
+    ) -> anyhow::Result<T> {
+        let choice =
+            self.show_choice_timed(branches.iter().map(|(label, _)| *label), seconds, default);
+        Self::take_branch(branches, choice.await?).await
+    }
+
+    async fn take_branch<T>(branches: Vec<Branch<'_, T>>, choice: usize) -> anyhow::Result<T> {
+        let (_, script) = branches
+            .into_iter()
+            .nth(choice)
+            .ok_or_else(|| anyhow::anyhow!("No branch for choice {}", choice))?;
+        script().await
+    }
+
+    /// Shows `portrait` beside the dialogue, along with the speaker's own box if they have one.
     fn show_portrait(&self, portrait: Option<(Portrait, PortraitOrientation)>) {
+        self.set_dialogue_box(portrait.and_then(|(p, _)| p.dialogue_box()));
         let mut this = self.0.borrow_mut();
         this.dialogue.portrait = portrait.map(|(p, o)| {
             (
@@ -920,13 +2596,228 @@ impl Game {
         });
     }
 
+    /// Writes the story state and the overworld as it currently stands.
+    fn save_progress(&self, path: &str) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct SaveData<'a> {
+            info: &'a Info,
+            overworld: &'a Overworld,
+            play_time: f32,
+        }
+
+        let this = self.0.borrow();
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer(
+            file,
+            &SaveData {
+                info: &this.info,
+                overworld: &this.overworld,
+                play_time: this.play_time,
+            },
+        )?;
+        Ok(())
+    }
+
+    fn show_notice(&self, text: impl Into<String>) {
+        self.0.borrow_mut().notice = Some(Notice {
+            text: text.into(),
+            remaining: NOTICE_DURATION,
+        });
+    }
+
+    /// Saves to the quicksave slot. Skipped mid-conversation, since the dialogue script's
+    /// progress can't be saved with the rest.
+    fn quicksave(&self) {
+        if self.0.borrow().dialogue_active() {
+            return;
+        }
+        match self.save_progress(QUICKSAVE_PATH) {
+            Ok(()) => self.show_notice("SAVED."),
+            Err(e) => println!("Failed to quicksave: {}", e),
+        }
+    }
+
+    async fn quickload(&self) {
+        if self.0.borrow().dialogue_active() {
+            return;
+        }
+        match self.load_progress(QUICKSAVE_PATH).await {
+            Ok(()) => self.show_notice("LOADED."),
+            Err(e) => println!("Failed to quickload: {}", e),
+        }
+    }
+
+    /// Opens the menu for saving to or loading from a slot, unless a conversation is going on.
+    fn open_save_menu(&self, spawner: &LocalSpawner) {
+        self.0
+            .borrow_mut()
+            .spawn_dialogue(spawner, save_menu(self.clone()));
+    }
+
+    /// Swaps in the map at `path`, carrying the player's inventory over and placing them at
+    /// `pos`.
+    async fn enter_map(&self, path: &str, pos: Vec2) -> anyhow::Result<()> {
+        let mut overworld: Overworld = serde_json::from_slice(&load_file(path).await?)?;
+        let mut this = self.0.borrow_mut();
+        let inventory = this
+            .overworld
+            .world
+            .get::<Inventory>(this.overworld.player)
+            .map(|inventory| (*inventory).clone());
+        if let Ok(inventory) = inventory {
+            overworld.world.insert_one(overworld.player, inventory)?;
+        }
+        if let Ok(Position(player_pos)) = overworld
+            .world
+            .query_one_mut::<&mut Position>(overworld.player)
+        {
+            *player_pos = pos;
+        }
+        this.overworld = overworld;
+        this.scripted_moves.clear();
+        Ok(())
+    }
+
+    async fn load_progress(&self, path: &str) -> anyhow::Result<()> {
+        #[derive(Deserialize)]
+        struct SaveData {
+            info: Info,
+            overworld: Overworld,
+            #[serde(default)]
+            play_time: f32,
+        }
+
+        let data: SaveData = serde_json::from_slice(&load_file(path).await?)?;
+        self.end_dialogue();
+        let mut this = self.0.borrow_mut();
+        this.info = data.info;
+        this.overworld = data.overworld;
+        this.play_time = data.play_time;
+        this.scripted_moves.clear();
+        this.input_locked = false;
+        Ok(())
+    }
+
+    /// Resolves with the next event that `filter` accepts.
+    fn wait_for_event(
+        &self,
+        filter: impl Fn(&Event) -> bool + 'static,
+    ) -> futures::channel::oneshot::Receiver<Event> {
+        let (s, r) = futures::channel::oneshot::channel();
+        self.0.borrow_mut().event_listeners.push(EventListener {
+            filter: Box::new(filter),
+            sender: s,
+        });
+        r
+    }
+
+    fn set_input_locked(&self, locked: bool) {
+        self.0.borrow_mut().input_locked = locked;
+    }
+
+    /// Walks `entity` in a straight line to `target`, resolving when it gets there.
+    fn move_entity(
+        &self,
+        entity: Entity,
+        target: Vec2,
+        speed: f32,
+    ) -> futures::channel::oneshot::Receiver<()> {
+        let (s, r) = futures::channel::oneshot::channel();
+        self.0.borrow_mut().scripted_moves.push(ScriptedMove {
+            entity,
+            target,
+            speed,
+            arrived: s,
+        });
+        r
+    }
+
+    fn give_item(&self, item: Ustr, count: u32) {
+        self.0.borrow_mut().overworld.give_item(item, count);
+    }
+
+    /// Takes `amount` off `entity`'s health and flashes its sprite. Returns whether the hit was
+    /// fatal.
+    fn damage(&self, entity: Entity, amount: u32) -> bool {
+        self.0.borrow_mut().overworld.damage(entity, amount)
+    }
+
+    fn has_item(&self, item: &str) -> bool {
+        self.0.borrow().overworld.item_count(ustr(item)) > 0
+    }
+
+    fn player(&self) -> Entity {
+        self.0.borrow().overworld.player
+    }
+
+    fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.0.borrow().overworld.find_by_name(ustr(name))
+    }
+
+    /// Runs `f` on one of the overworld's entities. The game stays borrowed until `f` returns,
+    /// so `f` mustn't call back into it.
+    fn with_entity<R>(&self, entity: Entity, f: impl FnOnce(EntityRef) -> R) -> anyhow::Result<R> {
+        let this = self.0.borrow();
+        Ok(f(this.overworld.world.entity(entity)?))
+    }
+
+    fn add_component<C: Component>(&self, entity: Entity, component: C) -> anyhow::Result<()> {
+        self.0
+            .borrow_mut()
+            .overworld
+            .world
+            .insert_one(entity, component)?;
+        Ok(())
+    }
+
+    /// Fades `entity`'s sprite towards `target` alpha by `speed` a step, from wherever it is now.
+    fn fade(&self, entity: Entity, target: f32, speed: f32) -> anyhow::Result<()> {
+        self.add_component(entity, FadeComponent { target, speed })
+    }
+
+    fn remove_component<C: Component>(&self, entity: Entity) -> anyhow::Result<C> {
+        Ok(self
+            .0
+            .borrow_mut()
+            .overworld
+            .world
+            .remove_one::<C>(entity)?)
+    }
+
+    /// Closes the dialogue box. The script that opened it keeps running; see `cancel_dialogue`.
     fn end_dialogue(&self) {
         let mut this = self.0.borrow_mut();
         this.dialogue.shown = false;
         this.dialogue.portrait = None;
         this.dialogue.choices = None;
         this.dialogue.current_choice = 0;
+        this.dialogue.choice_timer = None;
         this.dialogue.waiting_for = WaitingFor::Nothing;
+        this.dialogue.ninebox = None;
+    }
+
+    /// Swaps the dialogue's box texture, say for a speaker with their own look, until the
+    /// dialogue ends. `None` goes back to the usual box.
+    fn set_dialogue_box(&self, ninebox: Option<TextureId>) {
+        self.0.borrow_mut().dialogue.ninebox = ninebox;
+    }
+
+    /// How many characters of dialogue type out each tick. Carries over between conversations.
+    fn set_text_speed(&self, chars_per_tick: f32) {
+        self.0.borrow_mut().dialogue.chars_per_tick = chars_per_tick.max(0.0);
+    }
+
+    /// Stops the running conversation's script outright, then closes the dialogue box and
+    /// gives the player back control it may have locked.
+    fn cancel_dialogue(&self) {
+        if let Some(task) = self.0.borrow_mut().dialogue_task.take() {
+            task.handle.abort();
+        }
+        self.set_input_locked(false);
+        self.end_dialogue();
     }
 
     // fn dialogue_mut(&self) -> RefMut<Dialogue> {
@@ -940,11 +2831,66 @@ enum Portrait {
     Ghost,
 }
 
+impl Portrait {
+    /// The box this speaker's lines are drawn in, or `None` for the usual one.
+    fn dialogue_box(self) -> Option<TextureId> {
+        match self {
+            Portrait::Maribelle => None,
+            Portrait::Ghost => Some("ghostbox".into()),
+        }
+    }
+}
+
+async fn sign_dialogue(game: Game, text: String) -> anyhow::Result<()> {
+    game.show_portrait(None);
+    game.show_text(text).await?;
+    game.end_dialogue();
+    Ok(())
+}
+
+async fn chest_dialogue(game: Game, item: Option<Ustr>) -> anyhow::Result<()> {
+    game.show_portrait(None);
+    match item {
+        Some(item) => {
+            game.give_item(item, 1);
+            game.show_text(format!("YOU FOUND {}!", item.as_str().to_uppercase()))
+                .await?;
+        }
+        None => game.show_text("IT'S EMPTY.").await?,
+    }
+    game.end_dialogue();
+    Ok(())
+}
+
+/// Points the player back towards the lamp the first time they pick up some oil.
+async fn oil_hint(game: Game) -> anyhow::Result<()> {
+    game.wait_for_event(
+        |event| matches!(event, Event::PickupCollected { item, .. } if item.as_str() == "oil"),
+    )
+    .await?;
+    game.show_portrait(Some((Portrait::Maribelle, PortraitOrientation::Right)));
+    game.show_text("OIL! THAT LAMP COULD USE SOME.").await?;
+    game.end_dialogue();
+    Ok(())
+}
+
 async fn lamp_dialogue_tree(game: Game) -> anyhow::Result<()> {
     let m = Some((Portrait::Maribelle, PortraitOrientation::Right));
     game.show_portrait(m);
     game.show_text("IT'S A LAMP.").await?;
     game.show_text("I WISH IT WERE A BIT BRIGHTER...").await?;
+    let choice = game
+        .show_choice([
+            Choice::new("ADD SOME OIL", game.has_item("oil")),
+            Choice::from("LEAVE IT"),
+        ])
+        .await?;
+    if choice == 0 {
+        game.show_text("MAYBE SOME OF THIS OIL WOULD HELP.").await?;
+        game.damage(game.player(), 1);
+        game.show_text("{shake}OUCH! IT FLARED UP...\nMAYBE THAT WAS A LITTLE TOO MUCH.")
+            .await?;
+    }
     game.end_dialogue();
     Ok(())
 }
@@ -1057,6 +3003,20 @@ async fn ghost_customize_ghost_class(game: Game) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// A choice in a `Game::menu`: its label and the script to run when it's picked.
+type Branch<'a, T> = (
+    &'a str,
+    Box<dyn FnOnce() -> LocalBoxFuture<'a, anyhow::Result<T>> + 'a>,
+);
+
+fn branch<'a, T, F, Fut>(label: &'a str, script: F) -> Branch<'a, T>
+where
+    F: FnOnce() -> Fut + 'a,
+    Fut: Future<Output = anyhow::Result<T>> + 'a,
+{
+    (label, Box::new(move || script().boxed_local()))
+}
+
 async fn ghost_after(game: Game) -> anyhow::Result<()> {
     let m = Some((Portrait::Maribelle, PortraitOrientation::Right));
     let g = Some((Portrait::Ghost, PortraitOrientation::Left));
@@ -1067,66 +3027,148 @@ async fn ghost_after(game: Game) -> anyhow::Result<()> {
         player_class.ghost_title()
     ))
     .await?;
+    // Each branch says whether the conversation is over
     loop {
-        let choice = game.show_choice(["NOTHING", "US", "FIREBOLT"]).await?;
-        match choice {
-            0 => {
-                game.show_text(
-                    "THAT'S OKAY.  LET ME KNOW\nIF THERE'S ANYTHING YOU WANT TO CHANGE!",
-                )
-                .await?;
-                game.end_dialogue();
-                return Ok(());
-            }
-            1 => {
-                game.show_text_auto("WHICH OF US DO YOU WANT\nTO TALK ABOUT?")
+        let done = game
+            .menu(vec![
+                branch("NOTHING", || async {
+                    game.show_text(
+                        "THAT'S OKAY.  LET ME KNOW\nIF THERE'S ANYTHING YOU WANT TO CHANGE!",
+                    )
                     .await?;
-                let choice = game.show_choice(["NEVERMIND", "ME", "YOU"]).await?;
-                match choice {
-                    0 => {
-                        game.show_text_auto("OH, OKAY!\nWAS THERE ANYTHING ELSE?")
-                            .await?;
-                        continue;
-                    }
-                    1 => {
-                        game.show_portrait(m);
-                        game.show_text("I WANT TO BE SOMEONE DIFFERENT.").await?;
-                        game.show_portrait(g);
-                        game.show_text_auto("OKAY THEN!\nWHO ARE YOU?").await?;
-                        ghost_customize_player_class(game.clone()).await?;
-                        game.show_portrait(g);
-                        game.show_text_auto("IS THERE ANYTHING ELSE\nYOU WANT TO TALK ABOUT?")
-                            .await?;
-                        continue;
-                    }
-                    _ => {
-                        game.show_portrait(m);
-                        game.show_text("I WANT YOU TO BE SOMEONE ELSE.").await?;
-                        game.show_portrait(g);
-                        game.show_text("COOL!\nA CHANGE IS ALWAYS NICE.").await?;
-                        game.show_text_auto("UMM... WHAT AM I, EXACTLY?").await?;
-                        ghost_customize_ghost_class(game.clone()).await?;
-                        game.show_portrait(g);
-                        game.show_text_auto("IS THERE ANYTHING ELSE\nYOU WANT TO TALK ABOUT?")
-                            .await?;
-                        continue;
-                    }
-                }
-            }
-            _ => {
-                firebolt_dialogue_tree(game.clone()).await?;
-                game.show_portrait(g);
-                game.show_text_auto("IS THERE ANYTHING ELSE\nYOU WANT TO TALK ABOUT?")
+                    Ok(true)
+                }),
+                branch("US", || async {
+                    game.show_text_auto("WHICH OF US DO YOU WANT\nTO TALK ABOUT?")
+                        .await?;
+                    game.menu_timed(
+                        vec![
+                            branch("NEVERMIND", || async {
+                                game.show_text_auto("OH, OKAY!\nWAS THERE ANYTHING ELSE?")
+                                    .await?;
+                                Ok(())
+                            }),
+                            branch("ME", || async {
+                                game.show_portrait(m);
+                                game.show_text("I WANT TO BE SOMEONE DIFFERENT.").await?;
+                                game.show_portrait(g);
+                                game.show_text_auto("OKAY THEN!\nWHO ARE YOU?").await?;
+                                ghost_customize_player_class(game.clone()).await?;
+                                game.show_portrait(g);
+                                game.show_text_auto(
+                                    "IS THERE ANYTHING ELSE\nYOU WANT TO TALK ABOUT?",
+                                )
+                                .await?;
+                                Ok(())
+                            }),
+                            branch("YOU", || async {
+                                game.show_portrait(m);
+                                game.show_text("I WANT YOU TO BE SOMEONE ELSE.").await?;
+                                game.show_portrait(g);
+                                game.show_text("COOL!\nA CHANGE IS ALWAYS NICE.").await?;
+                                game.show_text_auto("UMM... WHAT AM I, EXACTLY?").await?;
+                                ghost_customize_ghost_class(game.clone()).await?;
+                                game.show_portrait(g);
+                                game.show_text_auto(
+                                    "IS THERE ANYTHING ELSE\nYOU WANT TO TALK ABOUT?",
+                                )
+                                .await?;
+                                Ok(())
+                            }),
+                        ],
+                        10.0,
+                        0,
+                    )
                     .await?;
-                continue;
-            }
+                    Ok(false)
+                }),
+                branch("THE LAMP", || async {
+                    ghost_lamp_errand(game.clone()).await?;
+                    game.show_portrait(g);
+                    game.show_text_auto("IS THERE ANYTHING ELSE\nYOU WANT TO TALK ABOUT?")
+                        .await?;
+                    Ok(false)
+                }),
+                branch("FIREBOLT", || async {
+                    firebolt_dialogue_tree(game.clone()).await?;
+                    game.show_portrait(g);
+                    game.show_text_auto("IS THERE ANYTHING ELSE\nYOU WANT TO TALK ABOUT?")
+                        .await?;
+                    Ok(false)
+                }),
+            ])
+            .await?;
+        if done {
+            game.end_dialogue();
+            return Ok(());
         }
     }
 }
 
-async fn ghost_meeting(game: Game, ghost: Entity) -> anyhow::Result<()> {
+/// The ghost stays put while Maribelle walks over to a lamp, then catches back up.
+async fn ghost_lamp_errand(game: Game) -> anyhow::Result<()> {
+    let g = Some((Portrait::Ghost, PortraitOrientation::Left));
+    let ghost = game
+        .find_by_name("ghost")
+        .ok_or_else(|| anyhow::anyhow!("No entity named ghost"))?;
+    game.show_portrait(g);
+    game.show_text("IT'S SO GLOOMY AROUND HERE...\nCOULD YOU GO LIGHT THE LAMP?")
+        .await?;
+    let follow = game.remove_component::<FollowComponent>(ghost)?;
+    game.show_text_until("I'LL WAIT RIGHT HERE!", |_, overworld| {
+        matches!(
+            overworld.interaction_target(overworld.player),
+            Some((_, _, InteractableType::Lamp))
+        )
+    })
+    .await?;
+    game.add_component(ghost, follow)?;
+    game.show_text("THAT'S THE ONE!\nWAIT UP, I'M COMING!")
+        .await?;
+    Ok(())
+}
+
+/// Resolves once `entity`'s current animation next wraps around, straight away if it has none.
+async fn wait_for_loop(game: &Game, entity: Entity) -> anyhow::Result<()> {
+    let animation = game.with_entity(entity, |entity| {
+        let mut animation = entity.get_mut::<AnimationComponent>()?;
+        let notified = std::mem::replace(&mut animation.notify_loops, true);
+        Some((animation.animation, notified))
+    })?;
+    if let Some((name, notified)) = animation {
+        game.wait_for_event(move |event| {
+            matches!(
+                event,
+                Event::AnimationLooped { entity: looped, animation }
+                    if *looped == entity && *animation == name
+            )
+        })
+        .await?;
+        game.with_entity(entity, |entity| {
+            if let Some(mut animation) = entity.get_mut::<AnimationComponent>() {
+                animation.notify_loops = notified;
+            }
+        })?;
+    }
+    Ok(())
+}
+
+async fn ghost_meeting(game: Game) -> anyhow::Result<()> {
+    let ghost = game
+        .find_by_name("ghost")
+        .ok_or_else(|| anyhow::anyhow!("No entity named ghost"))?;
     let m = Some((Portrait::Maribelle, PortraitOrientation::Right));
     let g = Some((Portrait::Ghost, PortraitOrientation::Left));
+    // The ghost phases in as it's summoned, and speaks up once it's been through its idle loop
+    game.with_entity(ghost, |entity| {
+        if let Some(mut sprite) = entity.get_mut::<SpriteComponent>() {
+            sprite.alpha = 0.0;
+        }
+    })?;
+    game.fade(ghost, 1.0, 0.02)?;
+    game.set_input_locked(true);
+    wait_for_loop(&game, ghost).await?;
+    game.set_input_locked(false);
     game.show_portrait(g);
     game.show_text_auto("HI THERE!\nWHO ARE YOU?").await?;
     ghost_customize_player_class(game.clone()).await?;
@@ -1145,20 +3187,23 @@ async fn ghost_meeting(game: Game, ghost: Entity) -> anyhow::Result<()> {
     ))
     .await?;
     game.end_dialogue();
-    let player = game.0.borrow().overworld.player;
-    game.0
-        .borrow_mut()
-        .overworld
-        .world
-        .insert_one(
-            ghost,
-            FollowComponent {
-                target: player,
-                max_distance: 64.0,
-                speed: 1.0,
-            },
-        )
-        .unwrap();
+    let player = game.player();
+    let player_pos = game
+        .with_entity(player, |entity| entity.get::<Position>().map(|pos| pos.0))?
+        .unwrap_or_default();
+    game.set_input_locked(true);
+    game.move_entity(ghost, player_pos + vec2(-32.0, 0.0), 1.0)
+        .await?;
+    game.set_input_locked(false);
+    game.add_component(
+        ghost,
+        FollowComponent {
+            target: player,
+            max_distance: 64.0,
+            speed: 1.0,
+            snap_to_axis: false,
+        },
+    )?;
 
     Ok(())
 }
@@ -1170,112 +3215,117 @@ async fn firebolt_dialogue_tree(game: Game) -> anyhow::Result<()> {
     game.show_portrait(g);
     game.show_text_auto("WOW!  SO THIS SPELL IS CALLED FIREBOLT!\nHOW STRONG IS IT?")
         .await?;
-    let (strength, cost) = loop {
+    // Strength and cost are 0 for the most and 2 for the least
+    loop {
         let strength = game
-            .show_choice(["VERY STRONG", "IT'S OK", "IT'S WEAK"])
-            .await?;
-        match strength {
-            0 => {
-                game.show_portrait(m);
-                game.show_text("IT'S SUPER STRONG.\nIT COULD PROBABLY KILL A DRAGON.")
-                    .await?;
-                game.show_portrait(g);
-                game.show_text(format!(
-                    "WOW! THAT'S SO COOL!\nYOU MUST BE A POWERFUL {}!",
-                    player_class.str()
-                ))
-                .await?;
-                game.show_text("SINCE IT'S SO STRONG,\nHOW MUCH MANA DOES IT COST?")
-                    .await?;
-            }
-            1 => {
-                game.show_portrait(m);
-                game.show_text("IT'S NOTHING SPECIAL.\nAN EVERYDAY SPELL FOR ME.")
-                    .await?;
-                game.show_portrait(g);
-                game.show_text("THAT'S NEAT!\nI BET YOU STUDIED HARD TO LEARN IT.")
-                    .await?;
-                game.show_text("SO SINCE IT'S AVERAGE STRENGTH,\nHOW MUCH MANA DOES IT COST?")
-                    .await?;
-            }
-            _ => {
-                game.show_portrait(m);
-                game.show_text("IT'S SUPER WEAK.\nI'M STILL LEARNING BETTER SPELLS...")
-                    .await?;
-                game.show_portrait(g);
-                game.show_text("AW, THAT'S OKAY.\nI BET YOU'LL GET STRONGER IN NO TIME!")
-                    .await?;
-                game.show_text("SO SINCE IT'S PRETTY WEAK,\nHOW MUCH MANA DOES IT COST?")
+            .menu(vec![
+                branch("VERY STRONG", || async {
+                    game.show_portrait(m);
+                    game.show_text("{shake}IT'S SUPER STRONG.\nIT COULD PROBABLY KILL A DRAGON.")
+                        .await?;
+                    game.show_portrait(g);
+                    game.show_text(format!(
+                        "WOW! THAT'S SO COOL!\nYOU MUST BE A POWERFUL {}!",
+                        player_class.str()
+                    ))
                     .await?;
-            }
-        }
-        let cost = game
-            .show_choice(["LOTS OF MANA", "NOT TOO MUCH", "BARELY ANY"])
+                    game.show_text("SINCE IT'S SO STRONG,\nHOW MUCH MANA DOES IT COST?")
+                        .await?;
+                    Ok(0)
+                }),
+                branch("IT'S OK", || async {
+                    game.show_portrait(m);
+                    game.show_text("IT'S NOTHING SPECIAL.\nAN EVERYDAY SPELL FOR ME.")
+                        .await?;
+                    game.show_portrait(g);
+                    game.show_text("THAT'S NEAT!\nI BET YOU STUDIED HARD TO LEARN IT.")
+                        .await?;
+                    game.show_text("SO SINCE IT'S AVERAGE STRENGTH,\nHOW MUCH MANA DOES IT COST?")
+                        .await?;
+                    Ok(1)
+                }),
+                branch("IT'S WEAK", || async {
+                    game.show_portrait(m);
+                    game.show_text("IT'S SUPER WEAK.\nI'M STILL LEARNING BETTER SPELLS...")
+                        .await?;
+                    game.show_portrait(g);
+                    game.show_text("AW, THAT'S OKAY.\nI BET YOU'LL GET STRONGER IN NO TIME!")
+                        .await?;
+                    game.show_text("SO SINCE IT'S PRETTY WEAK,\nHOW MUCH MANA DOES IT COST?")
+                        .await?;
+                    Ok(2)
+                }),
+            ])
             .await?;
-        match cost {
-            0 => {
-                game.show_portrait(m);
-                game.show_text("TONS.\nONLY THE MOST POWERFUL CAN WIELD IT.")
-                    .await?;
-                game.show_portrait(g);
-                match strength {
-                    0 => {
-                        game.show_text("WHOA. THAT'S ONLY FITTING\nFOR SUCH A POWERFUL SPELL!")
-                            .await?;
-                    }
-                    1 => {
-                        game.show_text(format!(
-                            "WOW. BEING A {} IS HARD...\nYOU'RE SO COOL!",
-                            player_class.str()
-                        ))
+        let cost = game
+            .menu(vec![
+                branch("LOTS OF MANA", || async {
+                    game.show_portrait(m);
+                    game.show_text("TONS.\nONLY THE MOST POWERFUL CAN WIELD IT.")
                         .await?;
-                    }
-                    _ => {
-                        game.show_text("WOW, THAT MUCH?\nMAYBE THIS SPELL ISN'T SO GOOD...")
-                            .await?;
-                    }
-                }
-            }
-            1 => {
-                game.show_portrait(m);
-                game.show_text("NOT TOO MUCH.\nI CAN HANDLE IT, EASY.")
-                    .await?;
-                game.show_portrait(g);
-                match strength {
-                    0 => {
-                        game.show_text("SUCH AN EFFICIENT SPELL!\nYOU'RE SO SMART!")
-                            .await?;
-                    }
-                    1 => {
-                        game.show_text("THAT'S A GREAT SPELL TO START WITH.\nGOOD THINKING!")
-                            .await?;
-                    }
-                    _ => {
-                        game.show_text("IT SOUNDS HARD TO USE,\nBUT I BET YOU'LL DO GREAT!")
+                    game.show_portrait(g);
+                    match strength {
+                        0 => {
+                            game.show_text("WHOA. THAT'S ONLY FITTING\nFOR SUCH A POWERFUL SPELL!")
+                                .await?;
+                        }
+                        1 => {
+                            game.show_text(format!(
+                                "WOW. BEING A {} IS HARD...\nYOU'RE SO COOL!",
+                                player_class.str()
+                            ))
                             .await?;
+                        }
+                        _ => {
+                            game.show_text("WOW, THAT MUCH?\nMAYBE THIS SPELL ISN'T SO GOOD...")
+                                .await?;
+                        }
                     }
-                }
-            }
-            _ => {
-                game.show_portrait(m);
-                game.show_text("IT'S SUPER CHEAP.\nI CAN CAST IT ALL DAY.")
-                    .await?;
-                game.show_portrait(g);
-                match strength {
-                    0 => {
-                        game.show_text("WOW... IS THAT THE STRONGEST SPELL?\nTHAT'S AMAZING! THIS'LL BE A BREEZE!").await?;
-                    }
-                    1 => {
-                        game.show_text("THAT'S GREAT! WE CAN GO\nON A WHILE WITHOUT RESTING!")
-                            .await?;
+                    Ok(0)
+                }),
+                branch("NOT TOO MUCH", || async {
+                    game.show_portrait(m);
+                    game.show_text("NOT TOO MUCH.\nI CAN HANDLE IT, EASY.")
+                        .await?;
+                    game.show_portrait(g);
+                    match strength {
+                        0 => {
+                            game.show_text("SUCH AN EFFICIENT SPELL!\nYOU'RE SO SMART!")
+                                .await?;
+                        }
+                        1 => {
+                            game.show_text("THAT'S A GREAT SPELL TO START WITH.\nGOOD THINKING!")
+                                .await?;
+                        }
+                        _ => {
+                            game.show_text("IT SOUNDS HARD TO USE,\nBUT I BET YOU'LL DO GREAT!")
+                                .await?;
+                        }
                     }
-                    _ => {
-                        game.show_text("THAT MAKES SENSE.\nIT'S GREAT TO HAVE OPTIONS!")
-                            .await?;
+                    Ok(1)
+                }),
+                branch("BARELY ANY", || async {
+                    game.show_portrait(m);
+                    game.show_text("IT'S SUPER CHEAP.\nI CAN CAST IT ALL DAY.")
+                        .await?;
+                    game.show_portrait(g);
+                    match strength {
+                        0 => {
+                            game.show_text("WOW... IS THAT THE STRONGEST SPELL?\nTHAT'S AMAZING! THIS'LL BE A BREEZE!").await?;
+                        }
+                        1 => {
+                            game.show_text("THAT'S GREAT! WE CAN GO\nON A WHILE WITHOUT RESTING!")
+                                .await?;
+                        }
+                        _ => {
+                            game.show_text("THAT MAKES SENSE.\nIT'S GREAT TO HAVE OPTIONS!")
+                                .await?;
+                        }
                     }
-                }
-            }
-        }
+                    Ok(2)
+                }),
+            ])
+            .await?;
         let strength_str = match strength {
             0 => "A VERY STRONG",
             1 => "A GOOD",
@@ -1291,28 +3341,174 @@ async fn firebolt_dialogue_tree(game: Game) -> anyhow::Result<()> {
             strength_str, cost_str
         ))
         .await?;
-        let confirm = game.show_choice(["YES", "ACTUALLY..."]).await?;
-        match confirm {
-            0 => {
-                game.show_text("GREAT! REMEMBER,\nYOU CAN ALWAYS CHANGE YOUR MIND!")
-                    .await?;
-                break (strength, cost);
-            }
-            _ => {
-                game.show_text("OH, WANNA GO OVER IT AGAIN?\nTHAT'S OKAY!")
-                    .await?;
-                game.show_text("SO THIS SPELL IS CALLED FIREBOLT!\nHOW STRONG IS IT?")
-                    .await?;
-                continue;
-            }
+        let confirmed = game
+            .menu(vec![
+                branch("YES", || async {
+                    game.show_text("GREAT! REMEMBER,\nYOU CAN ALWAYS CHANGE YOUR MIND!")
+                        .await?;
+                    Ok(true)
+                }),
+                branch("ACTUALLY...", || async {
+                    game.show_text("OH, WANNA GO OVER IT AGAIN?\nTHAT'S OKAY!")
+                        .await?;
+                    game.show_text("SO THIS SPELL IS CALLED FIREBOLT!\nHOW STRONG IS IT?")
+                        .await?;
+                    Ok(false)
+                }),
+            ])
+            .await?;
+        if confirmed {
+            break;
         }
-    };
+    }
 
     game.end_dialogue();
 
     Ok(())
 }
 
+fn save_slot_path(slot: usize) -> String {
+    format!("assets/saves/slot{}.json", slot + 1)
+}
+
+/// What the save menu shows about a slot, read without keeping the rest of the save.
+#[derive(Deserialize)]
+struct SaveSummary {
+    info: Info,
+    #[serde(default)]
+    play_time: f32,
+}
+
+impl SaveSummary {
+    async fn load(slot: usize) -> Option<Self> {
+        let bytes = load_file(&save_slot_path(slot)).await.ok()?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| println!("Failed to read save slot {}: {}", slot + 1, e))
+            .ok()
+    }
+
+    fn describe(&self) -> String {
+        let class = match &self.info.player_class {
+            Some(class) => class.str(),
+            None => "NO CLASS",
+        };
+        format!("{}, {}", class, play_time_words(self.play_time))
+    }
+}
+
+/// Spells out `n`, up to the hundreds, for the digitless font.
+fn number_words(n: u32) -> String {
+    const ONES: [&str; 20] = [
+        "ZERO",
+        "ONE",
+        "TWO",
+        "THREE",
+        "FOUR",
+        "FIVE",
+        "SIX",
+        "SEVEN",
+        "EIGHT",
+        "NINE",
+        "TEN",
+        "ELEVEN",
+        "TWELVE",
+        "THIRTEEN",
+        "FOURTEEN",
+        "FIFTEEN",
+        "SIXTEEN",
+        "SEVENTEEN",
+        "EIGHTEEN",
+        "NINETEEN",
+    ];
+    const TENS: [&str; 10] = [
+        "", "", "TWENTY", "THIRTY", "FORTY", "FIFTY", "SIXTY", "SEVENTY", "EIGHTY", "NINETY",
+    ];
+    match n {
+        0..=19 => ONES[n as usize].to_owned(),
+        20..=99 if n.is_multiple_of(10) => TENS[n as usize / 10].to_owned(),
+        20..=99 => format!("{} {}", TENS[n as usize / 10], ONES[n as usize % 10]),
+        100..=999 if n.is_multiple_of(100) => format!("{} HUNDRED", ONES[n as usize / 100]),
+        100..=999 => format!(
+            "{} HUNDRED {}",
+            ONES[n as usize / 100],
+            number_words(n % 100)
+        ),
+        _ => "MANY".to_owned(),
+    }
+}
+
+/// Formats as HH:MM.
+fn format_play_time(play_time: Duration) -> String {
+    let minutes = play_time.as_secs() / 60;
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+fn play_time_words(seconds: f32) -> String {
+    let minutes = (seconds / 60.0) as u32;
+    let count = |n: u32, unit: &str| match n {
+        1 => format!("ONE {}", unit),
+        n => format!("{} {}S", number_words(n), unit),
+    };
+    match (minutes / 60, minutes % 60) {
+        (0, 0) => "UNDER A MINUTE".to_owned(),
+        (0, m) => count(m, "MINUTE"),
+        (h, 0) => count(h, "HOUR"),
+        (h, m) => format!("{} {}", count(h, "HOUR"), count(m, "MINUTE")),
+    }
+}
+
+async fn save_menu(game: Game) -> anyhow::Result<()> {
+    let mut summaries = Vec::new();
+    for slot in 0..SAVE_SLOTS.len() {
+        summaries.push(SaveSummary::load(slot).await);
+    }
+    let overview: Vec<String> = SAVE_SLOTS
+        .iter()
+        .zip(&summaries)
+        .map(|(name, summary)| match summary {
+            Some(summary) => format!("{} SLOT, {}.", name, summary.describe()),
+            None => format!("{} SLOT, EMPTY.", name),
+        })
+        .collect();
+    game.show_portrait(None);
+    game.show_text(overview.join("\n")).await?;
+    let any_saved = summaries.iter().any(Option::is_some);
+    let action = game
+        .show_choice([
+            Choice::from("SAVE"),
+            Choice::new("LOAD", any_saved),
+            Choice::from("CANCEL"),
+        ])
+        .await?;
+    if action == 2 {
+        game.end_dialogue();
+        return Ok(());
+    }
+    let loading = action == 1;
+    let slot = game
+        .show_choice(
+            SAVE_SLOTS
+                .iter()
+                .zip(&summaries)
+                .map(|(name, summary)| Choice::new(*name, !loading || summary.is_some())),
+        )
+        .await?;
+    game.end_dialogue();
+    let path = save_slot_path(slot);
+    if loading {
+        match game.load_progress(&path).await {
+            Ok(()) => game.show_notice("LOADED."),
+            Err(e) => println!("Failed to load {}: {}", path, e),
+        }
+    } else {
+        match game.save_progress(&path) {
+            Ok(()) => game.show_notice("SAVED."),
+            Err(e) => println!("Failed to save {}: {}", path, e),
+        }
+    }
+    Ok(())
+}
+
 async fn wrap_dialogue(dialogue: impl Future<Output = anyhow::Result<()>>) {
     match dialogue.await {
         Ok(()) => (),
@@ -1323,31 +3519,330 @@ async fn wrap_dialogue(dialogue: impl Future<Output = anyhow::Result<()>>) {
     }
 }
 
+/// What the main loop is running.
+#[derive(Clone, Copy, PartialEq)]
+enum Scene {
+    Title,
+    Game,
+    Editor,
+}
+
+/// The most recently written of the quicksave and the save slots.
+fn latest_save() -> Option<String> {
+    std::iter::once(QUICKSAVE_PATH.to_owned())
+        .chain((0..SAVE_SLOTS.len()).map(save_slot_path))
+        .filter_map(|path| Some((std::fs::metadata(&path).ok()?.modified().ok()?, path)))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TitleChoice {
+    NewGame,
+    Continue,
+    Options,
+    Quit,
+}
+
+fn title_menu() -> Menu<TitleChoice> {
+    Menu::new(
+        "ILLUSORY FRIENDS",
+        vec![
+            (TitleChoice::NewGame, Choice::from("NEW GAME")),
+            (
+                TitleChoice::Continue,
+                Choice::new("CONTINUE", latest_save().is_some()),
+            ),
+            (TitleChoice::Options, Choice::from("OPTIONS")),
+            (TitleChoice::Quit, Choice::from("QUIT")),
+        ],
+    )
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PauseChoice {
+    Resume,
+    Save,
+    Options,
+    QuitToTitle,
+}
+
+fn pause_menu() -> Menu<PauseChoice> {
+    Menu::new(
+        "PAUSED",
+        vec![
+            (PauseChoice::Resume, Choice::from("RESUME")),
+            (PauseChoice::Save, Choice::from("SAVE")),
+            (PauseChoice::Options, Choice::from("OPTIONS")),
+            (PauseChoice::QuitToTitle, Choice::from("QUIT TO TITLE")),
+        ],
+    )
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OptionsChoice {
+    IntegerScale,
+    TextSpeed,
+    Back,
+}
+
+fn options_menu(config: &WindowConfig) -> Menu<OptionsChoice> {
+    let integer_scale = if config.integer_scale {
+        "INTEGER SCALE ON"
+    } else {
+        "INTEGER SCALE OFF"
+    };
+    let text_speed = match config.text_speed {
+        TextSpeed::Slow => "TEXT SPEED SLOW",
+        TextSpeed::Normal => "TEXT SPEED NORMAL",
+        TextSpeed::Fast => "TEXT SPEED FAST",
+    };
+    Menu::new(
+        "OPTIONS",
+        vec![
+            (OptionsChoice::IntegerScale, Choice::from(integer_scale)),
+            (OptionsChoice::TextSpeed, Choice::from(text_speed)),
+            (OptionsChoice::Back, Choice::from("BACK")),
+        ],
+    )
+}
+
+/// Applies and saves changes as they're picked, so they show straight away. Returns whether the
+/// menu was closed.
+fn update_options(menu: &mut Menu<OptionsChoice>, config: &mut WindowConfig, game: &Game) -> bool {
+    match menu.update() {
+        Some(OptionsChoice::IntegerScale) => {
+            config.integer_scale = !config.integer_scale;
+            game.0
+                .borrow_mut()
+                .camera
+                .set_integer_scale(config.integer_scale);
+        }
+        Some(OptionsChoice::TextSpeed) => {
+            config.text_speed = config.text_speed.next();
+            game.set_text_speed(config.text_speed.chars_per_tick());
+        }
+        Some(OptionsChoice::Back) => return true,
+        None => return is_key_pressed(KeyCode::Escape),
+    }
+    config
+        .save()
+        .unwrap_or_else(|e| println!("Failed to save {}: {}", CONFIG_PATH, e));
+    let current = menu.current;
+    *menu = options_menu(config);
+    menu.current = current;
+    false
+}
+
+/// A full-screen list of choices, for menus outside of dialogue.
+struct Menu<T> {
+    heading: &'static str,
+    choices: Vec<(T, Choice)>,
+    current: usize,
+}
+
+impl<T: Copy> Menu<T> {
+    fn new(heading: &'static str, choices: Vec<(T, Choice)>) -> Self {
+        Self {
+            heading,
+            choices,
+            current: 0,
+        }
+    }
+
+    /// Moves the cursor like the dialogue's choices do, returning whatever was picked.
+    fn update(&mut self) -> Option<T> {
+        let len = self.choices.len() as isize;
+        for (key, direction) in [(KeyCode::Up, -1), (KeyCode::Down, 1)] {
+            if !is_key_pressed(key) {
+                continue;
+            }
+            let mut choice = self.current as isize;
+            for _ in 0..len {
+                choice = (choice + direction).rem_euclid(len);
+                if self.choices[choice as usize].1.enabled {
+                    self.current = choice as usize;
+                    break;
+                }
+            }
+        }
+        if is_key_pressed(KeyCode::Space) || is_key_pressed(KeyCode::Enter) {
+            return self.choices.get(self.current).map(|(value, _)| *value);
+        }
+        None
+    }
+
+    fn draw(&self, assets: &Assets) {
+        let font = FontId::default();
+        draw_text_bmfont(
+            assets,
+            &font,
+            self.heading,
+            TextStyle {
+                x: 320.,
+                y: 96.,
+                color: colors::LIGHT,
+                justify: Justify::Center,
+                backing: TextBacking::Shadow,
+            },
+        );
+        let ninebox = assets.get(&assets.get_texture("ninebox"));
+        let height = 84. + 30. * self.choices.len() as f32;
+        draw_nine_box(*ninebox, 208., 160., 224., height);
+        for (i, (_, choice)) in self.choices.iter().enumerate() {
+            let text = if i == self.current {
+                format!("> {}", choice.text)
+            } else {
+                choice.text.clone()
+            };
+            draw_text_bmfont(
+                assets,
+                &font,
+                &text,
+                TextStyle {
+                    x: 320.,
+                    y: 192. + 30. * i as f32,
+                    color: if choice.enabled {
+                        colors::LIGHT
+                    } else {
+                        colors::DIM
+                    },
+                    justify: Justify::Center,
+                    backing: TextBacking::None,
+                },
+            );
+        }
+    }
+}
+
+/// Fonts aren't loaded yet, so this is just a bar.
+fn draw_loading_screen(progress: &LoadProgress) {
+    const WIDTH: f32 = 320.;
+    const HEIGHT: f32 = 16.;
+    let fraction = match progress.total() {
+        0 => 0.0,
+        total => progress.loaded() as f32 / total as f32,
+    };
+    let x = (screen_width() - WIDTH) / 2.0;
+    let y = (screen_height() - HEIGHT) / 2.0;
+    draw_rectangle(x, y, WIDTH * fraction, HEIGHT, colors::LIGHT);
+    draw_rectangle_lines(x, y, WIDTH, HEIGHT, 2.0, colors::LIGHT);
+}
+
 #[macroquad::main(window_conf)]
 async fn main() {
-    let mut assets = Assets::new().await.unwrap();
+    let progress = LoadProgress::default();
+    let mut loading = Box::pin(Assets::new(&progress));
+    let mut assets = loop {
+        if let Poll::Ready(assets) = futures::poll!(loading.as_mut()) {
+            break assets.unwrap();
+        }
+        clear_background(DARK);
+        draw_loading_screen(&progress);
+        next_frame().await
+    };
     // let mut overworld = Overworld::new(&assets);
     // let camera = Camera2D::from_display_rect(Rect::new(0.0, 0.0, 640.0, 360.0));
     let game = Game::new(&assets);
+    let mut config = WindowConfig::load();
+    game.0
+        .borrow_mut()
+        .camera
+        .set_integer_scale(config.integer_scale);
+    game.set_text_speed(config.text_speed.chars_per_tick());
     let mut editor = OverworldEditor::default();
-    editor
-        .load(&mut game.0.borrow_mut().overworld)
-        .await
-        .unwrap();
+    let mut scene = Scene::Title;
+    let mut title = title_menu();
+    let mut pause: Option<Menu<PauseChoice>> = None;
+    // Opens over either of the others
+    let mut options: Option<Menu<OptionsChoice>> = None;
+    // Whether a game has been played since startup, so New Game needs to start over
+    let mut started = false;
+    let mut debug_overlay = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--editor" => scene = Scene::Editor,
+            "--map" => match args.next() {
+                Some(path) => editor.set_map_path(path),
+                None => println!("--map needs a path"),
+            },
+            other => println!("Unknown argument: {}", other),
+        }
+    }
+    let overworld = editor.read_map().await.unwrap();
+    game.0.borrow_mut().overworld = overworld;
     let mut pool = futures::executor::LocalPool::new();
     let spawner = pool.spawner();
+    spawner
+        .spawn_local(wrap_dialogue(oil_hint(game.clone())))
+        .unwrap();
     // let mut dialogue = false;
-    let mut editor_enabled = false;
+    let mut accumulator = 0.0;
 
     loop {
         clear_background(DARK);
 
+        if scene == Scene::Title {
+            if let Some(menu) = &mut options {
+                // The key that closes it mustn't also pick something from the title menu
+                if update_options(menu, &mut config, &game) {
+                    options = None;
+                    game.draw_menu_screen(&assets, &title);
+                } else {
+                    game.draw_menu_screen(&assets, menu);
+                }
+                game.present();
+                next_frame().await;
+                continue;
+            }
+            match title.update() {
+                Some(TitleChoice::NewGame) => {
+                    if started {
+                        game.reset_progress();
+                        // The game can't stay borrowed while the map loads
+                        match editor.read_map().await {
+                            Ok(overworld) => game.0.borrow_mut().overworld = overworld,
+                            Err(e) => println!("Failed to load map: {}", e),
+                        }
+                    }
+                    started = true;
+                    scene = Scene::Game;
+                }
+                Some(TitleChoice::Continue) => {
+                    if let Some(path) = latest_save() {
+                        game.load_progress(&path)
+                            .await
+                            .unwrap_or_else(|e| println!("Failed to load {}: {}", path, e));
+                    }
+                    started = true;
+                    scene = Scene::Game;
+                }
+                Some(TitleChoice::Options) => options = Some(options_menu(&config)),
+                Some(TitleChoice::Quit) => break,
+                None => {}
+            }
+            game.draw_menu_screen(&assets, &title);
+            game.present();
+            next_frame().await;
+            continue;
+        }
+        let editor_enabled = scene == Scene::Editor;
+
         // set_camera(&camera);
 
         // overworld.update(&assets);
         // overworld.draw(&assets);
-        game.update(&assets, &spawner);
-        game.draw(&assets);
+        game.latch_input();
+        accumulator = (accumulator + get_frame_time()).min(TIMESTEP * MAX_STEPS_PER_FRAME as f32);
+        while accumulator >= TIMESTEP {
+            game.update(&assets, &spawner);
+            accumulator -= TIMESTEP;
+        }
+        game.draw(&assets, if editor_enabled { Some(&editor) } else { None });
+        if debug_overlay && !editor_enabled {
+            game.draw_debug_overlay();
+        }
         // if !dialogue {
         //     spawner
         //         .spawn_local(wrap_dialogue(firebolt_dialogue_tree(game.clone())))
@@ -1355,12 +3850,68 @@ async fn main() {
         //     dialogue = true;
         // }
 
+        // Handled after the update, so the key that closes the menu doesn't also reach the game
+        if let (Some(menu), Some(_)) = (&mut options, &pause) {
+            game.draw_menu_overlay(&assets, menu);
+            if update_options(menu, &mut config, &game) {
+                options = None;
+            }
+        } else if let Some(menu) = &mut pause {
+            game.draw_menu_overlay(&assets, menu);
+            let picked = if is_key_pressed(KeyCode::Escape) {
+                Some(PauseChoice::Resume)
+            } else {
+                menu.update()
+            };
+            if matches!(picked, Some(choice) if choice != PauseChoice::Options) {
+                pause = None;
+                game.set_paused(false);
+            }
+            match picked {
+                Some(PauseChoice::Save) => game.open_save_menu(&spawner),
+                Some(PauseChoice::Options) => options = Some(options_menu(&config)),
+                Some(PauseChoice::QuitToTitle) => {
+                    title = title_menu();
+                    scene = Scene::Title;
+                }
+                _ => {}
+            }
+        } else if !editor_enabled {
+            if is_key_pressed(KeyCode::F5) {
+                game.quicksave();
+            }
+            if is_key_pressed(KeyCode::F9) {
+                game.quickload().await;
+            }
+            // Never over a conversation, whose script would be frozen mid-line
+            if is_key_pressed(KeyCode::Escape) && !game.0.borrow().dialogue_active() {
+                pause = Some(pause_menu());
+                game.set_paused(true);
+            }
+        }
+
         if is_key_down(KeyCode::LeftShift) {
             if is_key_pressed(KeyCode::I) {
-                game.end_dialogue();
+                game.cancel_dialogue();
             }
             if is_key_pressed(KeyCode::J) {
-                editor_enabled = !editor_enabled;
+                scene = if editor_enabled {
+                    Scene::Game
+                } else {
+                    Scene::Editor
+                };
+            }
+            if is_key_pressed(KeyCode::O) {
+                debug_overlay = !debug_overlay;
+            }
+            if is_key_pressed(KeyCode::S) {
+                game.save_progress(PROGRESS_PATH)
+                    .unwrap_or_else(|e| println!("Failed to save progress: {}", e));
+            }
+            if is_key_pressed(KeyCode::L) {
+                game.load_progress(PROGRESS_PATH)
+                    .await
+                    .unwrap_or_else(|e| println!("Failed to load progress: {}", e));
             }
             if is_key_pressed(KeyCode::R) {
                 match assets.reload().await {
@@ -1373,8 +3924,115 @@ async fn main() {
         if editor_enabled {
             editor.update(&assets, &game).await;
         }
+        game.present();
+        if editor_enabled {
+            egui_macroquad::draw();
+        }
 
-        pool.run_until_stalled();
+        if pause.is_none() {
+            pool.run_until_stalled();
+        }
         next_frame().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wall(world: &mut World, bounds: Rect) {
+        world.spawn((Position(Vec2::ZERO), CollisionComponent { bounds }));
+    }
+
+    #[test]
+    fn resolve_penetrations_clears_an_inside_corner() {
+        let mut world = World::new();
+        // A floor and a wall to its left, meeting at (16, 32)
+        wall(&mut world, Rect::new(0.0, 32.0, 64.0, 16.0));
+        wall(&mut world, Rect::new(0.0, 0.0, 16.0, 48.0));
+        let boxed = world.spawn((
+            Position(vec2(12.0, 20.0)),
+            CollisionComponent {
+                bounds: Rect::new(0.0, 0.0, 16.0, 16.0),
+            },
+        ));
+        let mut overworld = Overworld {
+            world,
+            player: boxed,
+            ticks: 0,
+        };
+
+        overworld.resolve_penetrations(boxed);
+
+        let pos = overworld.world.get::<Position>(boxed).unwrap().0;
+        let resolved = Rect::new(pos.x, pos.y, 16.0, 16.0);
+        assert!(
+            resolved.left() >= 16.0,
+            "still inside the wall: {:?}",
+            resolved
+        );
+        assert!(
+            resolved.bottom() <= 32.0,
+            "still inside the floor: {:?}",
+            resolved
+        );
+    }
+
+    #[test]
+    fn vec2s_round_trip_as_pairs() {
+        let pos = Position(vec2(1.5, -2.0));
+        let json = serde_json::to_value(pos).unwrap();
+        assert_eq!(json, serde_json::json!([1.5, -2.0]));
+        let loaded: Position = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.0, pos.0);
+
+        let sprite = SpriteComponent {
+            texture: "ghost".into(),
+            offset: vec2(-8.0, 4.0),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(sprite).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "texture": { "TextureId": "ghost" },
+                "source": null,
+                "offset": [-8.0, 4.0],
+                "centered": false,
+                "flip_h": false,
+                "layer": 0,
+                "tint": [1.0, 1.0, 1.0, 1.0],
+                "sort_offset": 0.0,
+                "alpha": 1.0,
+            })
+        );
+        let loaded: SpriteComponent = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.offset, sprite.offset);
+    }
+
+    #[test]
+    fn dialogue_types_out_multi_byte_text_by_character() {
+        let text = "CAF\u{c9}\u{2019}S";
+        let mut dialogue = Dialogue::default();
+        dialogue.set_text(text.to_owned());
+        let count = text.chars().count();
+        for shown in 0..=count + 2 {
+            dialogue.current_progress = shown as f32;
+            let expected: String = text.chars().take(shown).collect();
+            assert_eq!(dialogue.shown_text(), expected);
+            assert_eq!(dialogue.fully_shown(), shown >= count);
+        }
+    }
+
+    #[test]
+    fn missing_glyphs_measure_as_the_fallback() {
+        let font = BMFont::new(
+            std::io::Cursor::new(&include_bytes!("../assets/font.fnt")[..]),
+            bmfont::OrdinateOrientation::TopToBottom,
+        )
+        .unwrap();
+        let (width, height) = measure_text(&font, "CAF\u{c9}\u{2019}S");
+        assert!(width > 0.0);
+        assert_eq!((width, height), measure_text(&font, "CAF??S"));
+    }
+}
@@ -1,3 +1,4 @@
+use macroquad::math::Vec2;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
@@ -20,6 +21,26 @@ impl Default for Rect {
 }
 
 impl Rect {
+    pub fn from_center(center: Vec2, size: Vec2) -> Rect {
+        Rect {
+            x: center.x - size.x / 2.0,
+            y: center.y - size.y / 2.0,
+            w: size.x,
+            h: size.y,
+        }
+    }
+
+    /// The rect spanning two opposite corners, given in either order.
+    pub fn from_points(a: Vec2, b: Vec2) -> Rect {
+        Rect {
+            x: a.x,
+            y: a.y,
+            w: b.x - a.x,
+            h: b.y - a.y,
+        }
+        .normalize()
+    }
+
     pub fn left(&self) -> f32 {
         self.x
     }
@@ -76,6 +97,40 @@ impl Rect {
             && self.bottom() >= other.top()
     }
 
+    /// The region covered by both rects, or `None` if they don't overlap. Rects that only touch
+    /// count as overlapping, like in `overlaps`, and give a zero-width or zero-height rect.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        if !self.overlaps(other) {
+            return None;
+        }
+        let x = self.left().max(other.left());
+        let y = self.top().max(other.top());
+        Some(Rect {
+            x,
+            y,
+            w: self.right().min(other.right()) - x,
+            h: self.bottom().min(other.bottom()) - y,
+        })
+    }
+
+    /// Moves (without resizing) this rect so it lies within `bounds`. Along any axis where it's
+    /// too big to fit, it's centred on `bounds` instead.
+    pub fn clamp_inside(&self, bounds: &Rect) -> Rect {
+        fn clamp_axis(start: f32, len: f32, bounds_start: f32, bounds_len: f32) -> f32 {
+            if len > bounds_len {
+                bounds_start + (bounds_len - len) / 2.0
+            } else {
+                start.max(bounds_start).min(bounds_start + bounds_len - len)
+            }
+        }
+        Rect {
+            x: clamp_axis(self.x, self.w, bounds.x, bounds.w),
+            y: clamp_axis(self.y, self.h, bounds.y, bounds.h),
+            w: self.w,
+            h: self.h,
+        }
+    }
+
     pub fn scale(&self, scale: f32) -> Rect {
         Rect {
             x: self.x * scale,
@@ -112,6 +167,70 @@ impl Rect {
     }
 }
 
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub struct Circle {
+    pub center: Vec2,
+    pub r: f32,
+}
+
+impl Circle {
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.center.distance_squared(point) <= self.r * self.r
+    }
+
+    pub fn overlaps(&self, other: &Circle) -> bool {
+        let reach = self.r + other.r;
+        self.center.distance_squared(other.center) <= reach * reach
+    }
+
+    pub fn overlaps_rect(&self, rect: &Rect) -> bool {
+        let closest = Vec2::new(
+            self.center.x.max(rect.left()).min(rect.right()),
+            self.center.y.max(rect.top()).min(rect.bottom()),
+        );
+        self.contains(closest)
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// One pixel in this direction.
+    pub fn step(self) -> Vec2 {
+        match self {
+            Direction::Up => Vec2::new(0.0, -1.0),
+            Direction::Down => Vec2::new(0.0, 1.0),
+            Direction::Left => Vec2::new(-1.0, 0.0),
+            Direction::Right => Vec2::new(1.0, 0.0),
+        }
+    }
+
+    /// The direction `v` mostly points in, with horizontal winning ties. `None` for zero.
+    pub fn dominant(v: Vec2) -> Option<Direction> {
+        if v == Vec2::ZERO {
+            None
+        } else if v.x.abs() >= v.y.abs() {
+            Some(if v.x < 0.0 {
+                Direction::Left
+            } else {
+                Direction::Right
+            })
+        } else {
+            Some(if v.y < 0.0 {
+                Direction::Up
+            } else {
+                Direction::Down
+            })
+        }
+    }
+}
+
 impl From<macroquad::math::Rect> for Rect {
     fn from(r: macroquad::math::Rect) -> Self {
         Self {
@@ -148,3 +267,150 @@ impl From<Rect> for macroquad::math::Rect {
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> Rect {
+        Rect { x, y, w, h }
+    }
+
+    #[test]
+    fn from_points_in_order() {
+        let r = Rect::from_points(Vec2::new(1.0, 2.0), Vec2::new(4.0, 6.0));
+        assert_eq!(r, rect(1.0, 2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn from_points_inverted() {
+        // b above and to the left of a
+        let r = Rect::from_points(Vec2::new(4.0, 6.0), Vec2::new(1.0, 2.0));
+        assert_eq!(r, rect(1.0, 2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn from_points_inverted_on_one_axis() {
+        let r = Rect::from_points(Vec2::new(4.0, 2.0), Vec2::new(1.0, 6.0));
+        assert_eq!(r, rect(1.0, 2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn clamp_inside_moves_a_rect_that_fits() {
+        let bounds = rect(0.0, 0.0, 100.0, 100.0);
+        assert_eq!(
+            rect(90.0, -5.0, 20.0, 20.0).clamp_inside(&bounds),
+            rect(80.0, 0.0, 20.0, 20.0)
+        );
+    }
+
+    #[test]
+    fn clamp_inside_centres_a_rect_too_wide() {
+        let bounds = rect(0.0, 0.0, 100.0, 100.0);
+        assert_eq!(
+            rect(-30.0, 90.0, 140.0, 20.0).clamp_inside(&bounds),
+            rect(-20.0, 80.0, 140.0, 20.0)
+        );
+    }
+
+    #[test]
+    fn clamp_inside_centres_a_rect_too_tall() {
+        let bounds = rect(0.0, 0.0, 100.0, 100.0);
+        assert_eq!(
+            rect(-5.0, 10.0, 20.0, 160.0).clamp_inside(&bounds),
+            rect(0.0, -30.0, 20.0, 160.0)
+        );
+    }
+
+    #[test]
+    fn clamp_inside_centres_a_rect_too_big_both_ways() {
+        let bounds = rect(10.0, 20.0, 100.0, 50.0);
+        assert_eq!(
+            rect(500.0, -500.0, 120.0, 90.0).clamp_inside(&bounds),
+            rect(0.0, 0.0, 120.0, 90.0)
+        );
+    }
+
+    #[test]
+    fn circle_contains_points_up_to_its_edge() {
+        let c = Circle {
+            center: Vec2::new(10.0, 10.0),
+            r: 5.0,
+        };
+        assert!(c.contains(Vec2::new(10.0, 10.0)));
+        assert!(c.contains(Vec2::new(15.0, 10.0)));
+        assert!(!c.contains(Vec2::new(14.0, 14.0)));
+    }
+
+    #[test]
+    fn circles_overlap_when_touching() {
+        let a = Circle {
+            center: Vec2::new(0.0, 0.0),
+            r: 3.0,
+        };
+        let touching = Circle {
+            center: Vec2::new(5.0, 0.0),
+            r: 2.0,
+        };
+        let apart = Circle {
+            center: Vec2::new(6.0, 0.0),
+            r: 2.0,
+        };
+        assert!(a.overlaps(&touching));
+        assert!(touching.overlaps(&a));
+        assert!(!a.overlaps(&apart));
+    }
+
+    #[test]
+    fn circle_overlaps_rect() {
+        let r = rect(0.0, 0.0, 10.0, 10.0);
+        let circle = |x, y, r| Circle {
+            center: Vec2::new(x, y),
+            r,
+        };
+        // Centre inside, even with no radius
+        assert!(circle(5.0, 5.0, 0.0).overlaps_rect(&r));
+        // Reaching an edge
+        assert!(circle(12.0, 5.0, 2.0).overlaps_rect(&r));
+        assert!(!circle(12.5, 5.0, 2.0).overlaps_rect(&r));
+        // Near a corner, where only the diagonal distance counts
+        assert!(circle(13.0, 14.0, 5.0).overlaps_rect(&r));
+        assert!(!circle(14.0, 14.0, 5.0).overlaps_rect(&r));
+    }
+
+    #[test]
+    fn intersection_of_a_contained_rect_is_itself() {
+        let inner = rect(2.0, 3.0, 4.0, 5.0);
+        let outer = rect(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(inner.intersection(&outer), Some(inner));
+        assert_eq!(outer.intersection(&inner), Some(inner));
+    }
+
+    #[test]
+    fn intersection_of_partly_overlapping_rects() {
+        let a = rect(0.0, 0.0, 10.0, 10.0);
+        let b = rect(6.0, -2.0, 10.0, 5.0);
+        assert_eq!(a.intersection(&b), Some(rect(6.0, 0.0, 4.0, 3.0)));
+        assert_eq!(b.intersection(&a), a.intersection(&b));
+    }
+
+    #[test]
+    fn intersection_of_touching_rects_is_empty_but_present() {
+        let a = rect(0.0, 0.0, 10.0, 10.0);
+        // Sharing an edge, like `overlaps` counts it
+        let beside = rect(10.0, 2.0, 5.0, 5.0);
+        assert!(a.overlaps(&beside));
+        assert_eq!(a.intersection(&beside), Some(rect(10.0, 2.0, 0.0, 5.0)));
+        // Sharing only a corner
+        let corner = rect(10.0, 10.0, 5.0, 5.0);
+        assert_eq!(a.intersection(&corner), Some(rect(10.0, 10.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn intersection_of_separate_rects_is_none() {
+        let a = rect(0.0, 0.0, 10.0, 10.0);
+        let b = rect(10.5, 0.0, 5.0, 5.0);
+        assert!(!a.overlaps(&b));
+        assert_eq!(a.intersection(&b), None);
+    }
+}
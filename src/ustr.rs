@@ -1,8 +1,79 @@
-use arrayvec::ArrayString;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
+
+/// An interned string of any length. Copying, comparing and hashing only touch an index into
+/// the pool; the text itself is looked up with `as_str`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Ustr(u32);
 
-pub type Ustr = ArrayString<32>;
 pub type UstrMap<V> = HashMap<Ustr, V>;
+
+/// Append-only, so every string handed out lives for the rest of the program.
+#[derive(Default)]
+struct Interner {
+    strings: Vec<&'static str>,
+    ids: HashMap<&'static str, u32>,
+}
+
+thread_local! {
+    // macroquad runs everything on the main thread, so the pool doesn't need to be shared
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner::default());
+}
+
+impl Ustr {
+    pub fn as_str(&self) -> &'static str {
+        INTERNER.with(|interner| interner.borrow().strings[self.0 as usize])
+    }
+}
+
+impl From<&str> for Ustr {
+    fn from(s: &str) -> Self {
+        INTERNER.with(|interner| {
+            let mut interner = interner.borrow_mut();
+            if let Some(&id) = interner.ids.get(s) {
+                return Ustr(id);
+            }
+            let string: &'static str = Box::leak(s.into());
+            let id = interner.strings.len() as u32;
+            interner.strings.push(string);
+            interner.ids.insert(string, id);
+            Ustr(id)
+        })
+    }
+}
+
+impl fmt::Display for Ustr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Ustr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Serialize for Ustr {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Ustr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(ustr(&String::deserialize(deserializer)?))
+    }
+}
+
 pub fn ustr(s: &str) -> Ustr {
-    Ustr::from(s).unwrap()
+    Ustr::from(s)
 }